@@ -0,0 +1,21 @@
+use std::io::{self, BufRead, Cursor, Read};
+
+use chess_rust::{uci, xboard};
+
+// the first command tells which protocol the GUI speaks, UCI unless it is "xboard"
+fn main() {
+    let stdin = io::stdin();
+    let mut first = String::new();
+    while first.trim().is_empty() {
+        if stdin.lock().read_line(&mut first).unwrap_or(0) == 0 {
+            return;
+        }
+    }
+
+    let input = Cursor::new(first.clone()).chain(stdin.lock());
+    if first.trim() == "xboard" {
+        xboard::run(input, io::stdout());
+    } else {
+        uci::run(input, io::stdout());
+    }
+}
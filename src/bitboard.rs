@@ -0,0 +1,126 @@
+use crate::chess::{is_white, Pieces};
+
+// bit i stands for board index i, so a8 is the lowest bit and h1 the highest
+
+pub fn bit(index: usize) -> u64 {
+    1 << index
+}
+
+// a8 and h1 are light
+pub const LIGHT_SQUARES: u64 = 0xAA55_AA55_AA55_AA55;
+
+// the board indices of the set bits, lowest first
+pub struct Squares(pub u64);
+
+impl Iterator for Squares {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None;
+        }
+        let index = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+
+        Some(index)
+    }
+}
+
+// (file, rank) steps, rank growing towards white's side of the board
+const KNIGHT_HOPS: [(i32, i32); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+const KING_STEPS: [(i32, i32); 8] = [(0, -1), (0, 1), (-1, 0), (1, 0), (-1, -1), (1, -1), (-1, 1), (1, 1)];
+const BLACK_PAWN_CAPTURES: [(i32, i32); 2] = [(-1, 1), (1, 1)];
+const WHITE_PAWN_CAPTURES: [(i32, i32); 2] = [(-1, -1), (1, -1)];
+
+// the four rook directions come first, then the four bishop ones
+const DIRECTIONS: [(i32, i32); 8] = KING_STEPS;
+
+const fn square_after(index: usize, step: (i32, i32)) -> Option<usize> {
+    let file = (index % 8) as i32 + step.0;
+    let rank = (index / 8) as i32 + step.1;
+
+    if file < 0 || file > 7 || rank < 0 || rank > 7 {
+        return None;
+    }
+    Some((8 * rank + file) as usize)
+}
+
+// squares one step away, for every starting square
+const fn leaper_table(steps: &[(i32, i32)]) -> [u64; 64] {
+    let mut table = [0; 64];
+
+    let mut index = 0;
+    while index < 64 {
+        let mut i = 0;
+        while i < steps.len() {
+            if let Some(target) = square_after(index, steps[i]) {
+                table[index] |= 1 << target;
+            }
+            i += 1;
+        }
+        index += 1;
+    }
+    table
+}
+
+// every square a slider sees in each direction on an empty board
+const fn ray_table() -> [[u64; 64]; 8] {
+    let mut table = [[0; 64]; 8];
+
+    let mut direction = 0;
+    while direction < 8 {
+        let mut index = 0;
+        while index < 64 {
+            let mut square = index;
+            while let Some(next) = square_after(square, DIRECTIONS[direction]) {
+                table[direction][index] |= 1 << next;
+                square = next;
+            }
+            index += 1;
+        }
+        direction += 1;
+    }
+    table
+}
+
+pub const KNIGHT_ATTACKS: [u64; 64] = leaper_table(&KNIGHT_HOPS);
+pub const KING_ATTACKS: [u64; 64] = leaper_table(&KING_STEPS);
+// indexed by whether the pawn is white
+pub const PAWN_ATTACKS: [[u64; 64]; 2] = [leaper_table(&BLACK_PAWN_CAPTURES), leaper_table(&WHITE_PAWN_CAPTURES)];
+const RAYS: [[u64; 64]; 8] = ray_table();
+
+// the ray up to and including its first blocker; rays running towards higher indices meet
+// their blocker at its lowest bit, the others at its highest, so no magic numbers or PEXT are needed
+fn ray_attacks(direction: usize, index: usize, occupied: u64) -> u64 {
+    let ray = RAYS[direction][index];
+    let blockers = ray & occupied;
+    if blockers == 0 {
+        return ray;
+    }
+
+    let (file, rank) = DIRECTIONS[direction];
+    let blocker = if 8 * rank + file > 0 { blockers.trailing_zeros() } else { 63 - blockers.leading_zeros() };
+
+    ray ^ RAYS[direction][blocker as usize]
+}
+
+pub fn rook_attacks(index: usize, occupied: u64) -> u64 {
+    (0..4).fold(0, |attacks, direction| attacks | ray_attacks(direction, index, occupied))
+}
+
+pub fn bishop_attacks(index: usize, occupied: u64) -> u64 {
+    (4..8).fold(0, |attacks, direction| attacks | ray_attacks(direction, index, occupied))
+}
+
+// squares the piece attacks from index, blocked by any piece of either color
+pub fn piece_attacks(piece: i8, index: usize, occupied: u64) -> u64 {
+    match Pieces::from_piece(piece) {
+        Some(Pieces::PAWN) => PAWN_ATTACKS[is_white(piece) as usize][index],
+        Some(Pieces::KNIG) => KNIGHT_ATTACKS[index],
+        Some(Pieces::BISH) => bishop_attacks(index, occupied),
+        Some(Pieces::ROOK) => rook_attacks(index, occupied),
+        Some(Pieces::QUEE) => bishop_attacks(index, occupied) | rook_attacks(index, occupied),
+        Some(Pieces::KING) => KING_ATTACKS[index],
+        None => 0,
+    }
+}
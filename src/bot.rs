@@ -1,14 +1,317 @@
-use crate::chess::{self, generate_all_legal_moves};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
-pub fn turn(is_white_turn: bool, board: &Vec<i8>, bitboard: u64, en_passant_index: usize) -> (usize, usize) {
-    let legal_moves = generate_all_legal_moves(is_white_turn, board, bitboard, en_passant_index);
+use crate::chess::{is_white, Move, Position};
 
+pub const MATE: i32 = 100_000;
+const INFINITY: i32 = 1_000_000;
 
-    for piece in legal_moves.clone().into_keys() {
-        let move_vec = legal_moves[&piece].clone();
-        if move_vec.len() != 0 {
-            return (piece, move_vec[0]);
+// indexed by piece type - 1
+const PIECE_VALUES: [i32; 6] = [100, 330, 320, 500, 900, 20_000];
+
+// piece-square tables from white's point of view, index 0 being a8 like the board
+#[rustfmt::skip]
+const PIECE_SQUARE_TABLES: [[i32; 64]; 6] = [
+    // pawn
+    [
+         0,   0,   0,   0,   0,   0,   0,   0,
+        50,  50,  50,  50,  50,  50,  50,  50,
+        10,  10,  20,  30,  30,  20,  10,  10,
+         5,   5,  10,  25,  25,  10,   5,   5,
+         0,   0,   0,  20,  20,   0,   0,   0,
+         5,  -5, -10,   0,   0, -10,  -5,   5,
+         5,  10,  10, -20, -20,  10,  10,   5,
+         0,   0,   0,   0,   0,   0,   0,   0,
+    ],
+    // bishop
+    [
+       -20, -10, -10, -10, -10, -10, -10, -20,
+       -10,   0,   0,   0,   0,   0,   0, -10,
+       -10,   0,   5,  10,  10,   5,   0, -10,
+       -10,   5,   5,  10,  10,   5,   5, -10,
+       -10,   0,  10,  10,  10,  10,   0, -10,
+       -10,  10,  10,  10,  10,  10,  10, -10,
+       -10,   5,   0,   0,   0,   0,   5, -10,
+       -20, -10, -10, -10, -10, -10, -10, -20,
+    ],
+    // knight
+    [
+       -50, -40, -30, -30, -30, -30, -40, -50,
+       -40, -20,   0,   0,   0,   0, -20, -40,
+       -30,   0,  10,  15,  15,  10,   0, -30,
+       -30,   5,  15,  20,  20,  15,   5, -30,
+       -30,   0,  15,  20,  20,  15,   0, -30,
+       -30,   5,  10,  15,  15,  10,   5, -30,
+       -40, -20,   0,   5,   5,   0, -20, -40,
+       -50, -40, -30, -30, -30, -30, -40, -50,
+    ],
+    // rook
+    [
+         0,   0,   0,   0,   0,   0,   0,   0,
+         5,  10,  10,  10,  10,  10,  10,   5,
+        -5,   0,   0,   0,   0,   0,   0,  -5,
+        -5,   0,   0,   0,   0,   0,   0,  -5,
+        -5,   0,   0,   0,   0,   0,   0,  -5,
+        -5,   0,   0,   0,   0,   0,   0,  -5,
+        -5,   0,   0,   0,   0,   0,   0,  -5,
+         0,   0,   0,   5,   5,   0,   0,   0,
+    ],
+    // queen
+    [
+       -20, -10, -10,  -5,  -5, -10, -10, -20,
+       -10,   0,   0,   0,   0,   0,   0, -10,
+       -10,   0,   5,   5,   5,   5,   0, -10,
+        -5,   0,   5,   5,   5,   5,   0,  -5,
+         0,   0,   5,   5,   5,   5,   0,  -5,
+       -10,   5,   5,   5,   5,   5,   0, -10,
+       -10,   0,   5,   0,   0,   0,   0, -10,
+       -20, -10, -10,  -5,  -5, -10, -10, -20,
+    ],
+    // king
+    [
+       -30, -40, -40, -50, -50, -40, -40, -30,
+       -30, -40, -40, -50, -50, -40, -40, -30,
+       -30, -40, -40, -50, -50, -40, -40, -30,
+       -30, -40, -40, -50, -50, -40, -40, -30,
+       -20, -30, -30, -40, -40, -30, -30, -20,
+       -10, -20, -20, -20, -20, -20, -20, -10,
+        20,  20,   0,   0,   0,   0,  20,  20,
+        20,  30,  10,   0,   0,  10,  30,  20,
+    ],
+];
+
+// a search stops at whichever limit comes first, with neither it only ends on a forced mate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    pub depth: Option<u32>,
+    pub time: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchInfo {
+    pub depth: u32,
+    // centipawns from the side to move, MATE - n when it mates in n plies
+    pub score: i32,
+    pub nodes: u64,
+    pub time: Duration,
+    pub pv: Vec<Move>,
+}
+
+impl SearchInfo {
+    pub fn best_move(&self) -> Option<Move> {
+        self.pv.first().copied()
+    }
+}
+
+// time kept aside for the GUI and the pipe when a clock is running
+pub const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+
+// an even share of the clock over the moves left until the next time control, never more than half of it
+pub fn time_for_move(clock: Duration, increment: Duration, moves_to_go: u32) -> Duration {
+    let share = (clock / moves_to_go.max(1) + increment / 2).min(clock / 2);
+
+    share.saturating_sub(MOVE_OVERHEAD).max(Duration::from_millis(1))
+}
+
+fn piece_value(piece: i8) -> i32 {
+    PIECE_VALUES[(piece & 7) as usize - 1]
+}
+
+// material and piece placement, from the point of view of the side to move
+pub fn evaluate(position: &Position) -> i32 {
+    if position.insufficient_material() {
+        return 0;
+    }
+    let mut score = 0;
+
+    for (index, piece) in position.board.iter().enumerate() {
+        if *piece == 0 {
+            continue;
         }
+        // black reads the tables upside down
+        let square = if is_white(*piece) { index } else { index ^ 56 };
+        let value = piece_value(*piece) + PIECE_SQUARE_TABLES[(piece & 7) as usize - 1][square];
+
+        score += if is_white(*piece) { value } else { -value };
+    }
+
+    if position.is_white_turn { score } else { -score }
+}
+
+fn is_noisy(mv: &Move) -> bool {
+    mv.is_capture() || mv.promotion.is_some()
+}
+
+// previous best move first, then captures by most valuable victim and least valuable attacker
+fn order_moves(moves: &mut [Move], best: Option<Move>) {
+    moves.sort_by_cached_key(|mv| {
+        if Some(*mv) == best {
+            return i32::MIN;
+        }
+        let mut score = 0;
+        if mv.is_capture() {
+            score -= 10 * piece_value(mv.captured) - piece_value(mv.piece);
+        }
+        if let Some(promotion) = mv.promotion {
+            score -= piece_value(promotion as i8);
+        }
+        score
+    });
+}
+
+struct Searcher<'a> {
+    root_depth: u32,
+    start: Instant,
+    time: Option<Duration>,
+    stop: &'a AtomicBool,
+    nodes: u64,
+    aborted: bool,
+}
+
+impl Searcher<'_> {
+    fn out_of_time(&mut self) -> bool {
+        if !self.aborted && self.nodes.is_multiple_of(1024) {
+            self.aborted = self.stop.load(Ordering::Relaxed)
+                || self.time.is_some_and(|time| self.start.elapsed() >= time);
+        }
+        self.aborted
+    }
+
+    // only captures and promotions are searched past the horizon, so the evaluation is taken on a quiet board
+    fn quiescence(&mut self, position: &mut Position, ply: i32, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.out_of_time() {
+            return 0;
+        }
+
+        let moves = position.legal_move_list();
+        if moves.is_empty() {
+            return if position.in_check() { -MATE + ply } else { 0 };
+        }
+
+        let stand_pat = evaluate(position);
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+
+        let mut moves: Vec<Move> = moves.into_iter().filter(is_noisy).collect();
+        order_moves(&mut moves, None);
+
+        let mut best = stand_pat;
+        for mv in moves {
+            let undo = position.play(mv);
+            let score = -self.quiescence(position, ply + 1, -beta, -alpha);
+            position.unmake_move(&undo);
+
+            if self.aborted {
+                return 0;
+            }
+            best = best.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        best
+    }
+
+    // pv receives the principal variation below this node, previous_pv is the one of the last iteration;
+    // moves are played on position and taken back, leaving it as it was
+    fn negamax(&mut self, position: &mut Position, depth: u32, mut alpha: i32, beta: i32, pv: &mut Vec<Move>, previous_pv: &[Move]) -> i32 {
+        let ply = (self.root_depth - depth) as i32;
+        if depth == 0 {
+            return self.quiescence(position, ply, alpha, beta);
+        }
+        self.nodes += 1;
+        if self.out_of_time() {
+            return 0;
+        }
+        // going back to a position already seen is taken as a draw, the same line could be played again
+        if ply > 0 && (position.repetitions() > 0 || position.halfmove_clock >= 100 || position.insufficient_material()) {
+            return 0;
+        }
+
+        let mut moves = position.legal_move_list();
+        if moves.is_empty() {
+            return if position.in_check() { -MATE + ply } else { 0 };
+        }
+        order_moves(&mut moves, previous_pv.first().copied());
+
+        let mut best = -INFINITY;
+        for mv in moves {
+            // the last iteration's line is only worth following while we are still on it
+            let child_previous_pv = if previous_pv.first() == Some(&mv) { &previous_pv[1..] } else { &[] };
+            let mut child_pv: Vec<Move> = Vec::new();
+
+            let undo = position.play(mv);
+            let score = -self.negamax(position, depth - 1, -beta, -alpha, &mut child_pv, child_previous_pv);
+            position.unmake_move(&undo);
+
+            if self.aborted {
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+            }
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(mv);
+                pv.extend(child_pv);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+        best
+    }
+}
+
+// iterative deepening negamax with alpha-beta pruning, keeping the last fully searched depth
+pub fn search(position: &Position, limits: &Limits) -> SearchInfo {
+    search_until(position, limits, &AtomicBool::new(false), |_| ())
+}
+
+// same as search, but also gives up as soon as stop is set and reports every completed depth
+pub fn search_until(position: &Position, limits: &Limits, stop: &AtomicBool, mut report: impl FnMut(&SearchInfo)) -> SearchInfo {
+    let mut searcher = Searcher {
+        root_depth: 0,
+        start: Instant::now(),
+        time: limits.time,
+        stop,
+        nodes: 0,
+        aborted: false,
+    };
+    let mut info = SearchInfo { depth: 0, score: 0, nodes: 0, time: Duration::ZERO, pv: Vec::new() };
+    let mut root = position.clone();
+
+    for depth in 1..=limits.depth.unwrap_or(u32::MAX) {
+        let mut pv: Vec<Move> = Vec::new();
+        searcher.root_depth = depth;
+        let score = searcher.negamax(&mut root, depth, -INFINITY, INFINITY, &mut pv, &info.pv);
+        if searcher.aborted {
+            break;
+        }
+
+        info = SearchInfo { depth, score, nodes: searcher.nodes, time: searcher.start.elapsed(), pv };
+        report(&info);
+
+        if info.pv.is_empty() || score.abs() >= MATE - depth as i32 {
+            break;
+        }
+        // the next depth takes several times longer, no point starting what cannot finish
+        if let Some(time) = limits.time && searcher.start.elapsed() * 2 >= time {
+            break;
+        }
+    }
+
+    // even when the clock ran out before depth 1 finished there has to be something to play
+    if info.pv.is_empty() && let Some(mv) = position.legal_move_list().first() {
+        info.pv.push(*mv);
     }
-    (0, 0)
-}
\ No newline at end of file
+    info.nodes = searcher.nodes;
+    info.time = searcher.start.elapsed();
+    info
+}
@@ -1,17 +1,71 @@
-use std::collections::HashMap;
+use std::fmt;
 
-#[derive(Debug)]
+use crate::bitboard::{bit, piece_attacks, Squares, bishop_attacks, rook_attacks, KING_ATTACKS, KNIGHT_ATTACKS, LIGHT_SQUARES, PAWN_ATTACKS};
+use crate::zobrist::{castling_key, en_passant_key, piece_key, BLACK_TO_MOVE};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum MoveResult {
     Capture(Pieces),
     Castle,
     Check,
-    Checkmate,
+    GameOver(Outcome),
     Fail,
     Move,
-    Promotion(usize),
+    Promotion(Pieces),
+    // a draw the players may claim, the game goes on unless they do
+    DrawClaimable(Outcome),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Checkmate { white_wins: bool },
+    Stalemate,
+    // claimable on the third occurrence of a position, automatic on the fifth
+    ThreefoldRepetition,
+    FivefoldRepetition,
+    // claimable after fifty moves by each side without a capture or pawn move, automatic after seventy-five
+    FiftyMoveRule,
+    SeventyFiveMoveRule,
+    // neither side has the pieces left to give mate
+    InsufficientMaterial,
+    // a flag fell, which is only a loss if the opponent could still have mated
+    Timeout { white_wins: bool },
+    TimeoutVsInsufficientMaterial,
+}
+
+impl Outcome {
+    // None when the game is drawn
+    pub fn winner(&self) -> Option<bool> {
+        match self {
+            Outcome::Checkmate { white_wins } | Outcome::Timeout { white_wins } => Some(*white_wins),
+            _ => None,
+        }
+    }
+
+    pub fn is_draw(&self) -> bool {
+        self.winner().is_none()
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::Checkmate { white_wins: true } => write!(f, "White wins by checkmate"),
+            Outcome::Checkmate { white_wins: false } => write!(f, "Black wins by checkmate"),
+            Outcome::Stalemate => write!(f, "Draw by stalemate"),
+            Outcome::ThreefoldRepetition => write!(f, "Draw by threefold repetition"),
+            Outcome::FivefoldRepetition => write!(f, "Draw by fivefold repetition"),
+            Outcome::FiftyMoveRule => write!(f, "Draw by the fifty-move rule"),
+            Outcome::SeventyFiveMoveRule => write!(f, "Draw by the seventy-five-move rule"),
+            Outcome::InsufficientMaterial => write!(f, "Draw by insufficient material"),
+            Outcome::Timeout { white_wins: true } => write!(f, "White wins on time"),
+            Outcome::Timeout { white_wins: false } => write!(f, "Black wins on time"),
+            Outcome::TimeoutVsInsufficientMaterial => write!(f, "Draw by timeout vs insufficient material"),
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[repr(i8)]
 pub enum Pieces {
     PAWN = 1,
@@ -22,25 +76,181 @@ pub enum Pieces {
     KING = 6,
 }
 
-enum Directions {
-    UP = -8,
-    DOWN = 8,
-    LEFT = -1,
-    RIGHT = 1,
+impl Pieces {
+    // ignores the color bit, returns None for an empty square
+    pub fn from_piece(piece: i8) -> Option<Pieces> {
+        match piece & 7 {
+            1 => Some(Pieces::PAWN),
+            2 => Some(Pieces::BISH),
+            3 => Some(Pieces::KNIG),
+            4 => Some(Pieces::ROOK),
+            5 => Some(Pieces::QUEE),
+            6 => Some(Pieces::KING),
+            _ => None,
+        }
+    }
 }
 
-pub fn xy_dir(direction: i32) -> i32 {
-    direction.abs() / direction
+pub const PROMOTIONS: [Pieces; 4] = [Pieces::QUEE, Pieces::ROOK, Pieces::BISH, Pieces::KNIG];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MoveFlag {
+    Normal,
+    DoublePush,
+    EnPassant,
+    Castle,
 }
 
-pub fn is_different_color(piece1: i8, piece2: i8) -> bool {
-    is_white(piece1) != is_white(piece2)
+// a move along with what it does to the board, so it can be played or written down without looking at the board again
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Move {
+    pub from: usize,
+    pub to: usize,
+    // pieces keep their color bit, captured is 0 when nothing is taken
+    pub piece: i8,
+    pub captured: i8,
+    pub promotion: Option<Pieces>,
+    pub flag: MoveFlag,
+}
+
+// what a move overwrites, so it can be taken back
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Undo {
+    pub mv: Move,
+    pub castling_rights: CastlingRights,
+    pub en_passant: Option<usize>,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+}
+
+impl Move {
+    pub fn is_capture(&self) -> bool {
+        self.captured != 0
+    }
+
+    // "e2e4" or "e7e8q"
+    pub fn name(&self) -> String {
+        move_name(self.from, self.to, self.promotion)
+    }
 }
 
-pub fn is_sliding_piece(piece_type: i8) -> bool {
-    piece_type == Pieces::BISH as i8
-    || piece_type == Pieces::QUEE as i8
-    || piece_type == Pieces::ROOK as i8
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CastlingRights {
+    pub white_king_side: bool,
+    pub white_queen_side: bool,
+    pub black_king_side: bool,
+    pub black_queen_side: bool,
+    // files of the rooks each right castles with, indexed by whether the side is white then by
+    // whether it is the king side; the a and h files unless the game is Chess960
+    pub rook_files: [[i32; 2]; 2],
+}
+
+impl CastlingRights {
+    pub fn all() -> CastlingRights {
+        CastlingRights {
+            white_king_side: true,
+            white_queen_side: true,
+            black_king_side: true,
+            black_queen_side: true,
+            rook_files: [[0, 7], [0, 7]],
+        }
+    }
+
+    pub fn none() -> CastlingRights {
+        CastlingRights {
+            white_king_side: false,
+            white_queen_side: false,
+            black_king_side: false,
+            black_queen_side: false,
+            rook_files: [[0, 7], [0, 7]],
+        }
+    }
+
+    pub fn can_castle(&self, white: bool, king_side: bool) -> bool {
+        match (white, king_side) {
+            (true, true) => self.white_king_side,
+            (true, false) => self.white_queen_side,
+            (false, true) => self.black_king_side,
+            (false, false) => self.black_queen_side,
+        }
+    }
+
+    pub fn grant(&mut self, white: bool, king_side: bool) {
+        match (white, king_side) {
+            (true, true) => self.white_king_side = true,
+            (true, false) => self.white_queen_side = true,
+            (false, true) => self.black_king_side = true,
+            (false, false) => self.black_queen_side = true,
+        }
+    }
+
+    pub fn revoke(&mut self, white: bool, king_side: bool) {
+        match (white, king_side) {
+            (true, true) => self.white_king_side = false,
+            (true, false) => self.white_queen_side = false,
+            (false, true) => self.black_king_side = false,
+            (false, false) => self.black_queen_side = false,
+        }
+    }
+
+    pub fn rook_file(&self, white: bool, king_side: bool) -> i32 {
+        self.rook_files[white as usize][king_side as usize]
+    }
+
+    // grants the right to castle with the rook on that file
+    pub fn grant_with_rook(&mut self, white: bool, king_side: bool, file: i32) {
+        self.grant(white, king_side);
+        self.rook_files[white as usize][king_side as usize] = file;
+    }
+
+    // the king moving, or a rook leaving or being captured on the square of a right, loses it for good
+    pub fn update(&mut self, piece: i8, original_index: usize, index: usize) {
+        if piece & 7 == Pieces::KING as i8 {
+            self.revoke(is_white(piece), true);
+            self.revoke(is_white(piece), false);
+        }
+
+        for white in [true, false] {
+            for king_side in [true, false] {
+                let rook = index_of(self.rook_file(white, king_side), back_rank(white));
+                if rook == original_index || rook == index {
+                    self.revoke(white, king_side);
+                }
+            }
+        }
+    }
+
+    // drops the rights whose rook is missing or not on the right side of a king standing on the back rank
+    pub fn restricted_to(&self, board: &[i8]) -> CastlingRights {
+        let mut castling_rights = *self;
+
+        for white in [true, false] {
+            let color = if white { 8 } else { 0 };
+            let rank = back_rank(white);
+            let king_file = (0..8).find(|file| board[index_of(*file, rank)] == Pieces::KING as i8 + color);
+
+            for king_side in [true, false] {
+                let rook_file = self.rook_file(white, king_side);
+                let in_place = king_file.is_some_and(|king_file| (rook_file > king_file) == king_side)
+                    && board[index_of(rook_file, rank)] == Pieces::ROOK as i8 + color;
+
+                if !in_place {
+                    castling_rights.revoke(white, king_side);
+                }
+            }
+        }
+        castling_rights
+    }
+}
+
+// the rank the pieces of a side start on
+pub fn back_rank(white: bool) -> i32 {
+    if white { 7 } else { 0 }
+}
+
+// every square of a rank from one index to the other, both included
+fn rank_span(from: usize, to: usize) -> u64 {
+    (from.min(to)..=from.max(to)).fold(0, |squares, index| squares | bit(index))
 }
 
 pub fn is_white(piece: i8) -> bool {
@@ -63,276 +273,554 @@ pub fn index_of(file: i32, rank: i32) -> usize {
     (8 * rank + file) as usize
 }
 
-pub fn in_bounds(file: i32, rank: i32) -> bool {
-    file < 8 && file >= 0 && rank < 8 && rank >= 0
-}
+// "e4" style names, rank 8 being the top of the board
+pub fn square_name(index: usize) -> String {
+    let file = (b'a' + file_of(index) as u8) as char;
+    let rank = (b'8' - rank_of(index) as u8) as char;
 
-pub fn is_empty(board: &Vec<i8>, file: i32, rank: i32) -> bool {
-    in_bounds(file, rank) && board[index_of(file, rank)] == 0
+    format!("{}{}", file, rank)
 }
 
-pub fn is_enemy(board: &Vec<i8>, white: bool, file: i32, rank: i32) -> bool {
-    in_bounds(file, rank)
-    && !is_empty(board, file, rank)
-    && is_white(board[index_of(file, rank)]) != white
+pub fn square_from_name(name: &str) -> Option<usize> {
+    let mut chars = name.chars();
+    let file = chars.next()? as i32 - 'a' as i32;
+    let rank = '8' as i32 - chars.next()? as i32;
+
+    if chars.next().is_some() || !in_bounds(file, rank) {
+        return None;
+    }
+    Some(index_of(file, rank))
 }
 
-pub fn is_empty_or_enemy(board: Vec<i8>, white: bool, file: i32, rank: i32) -> bool {
-    is_empty(&board, file, rank) 
-    || is_enemy(&board, white, file, rank)
+// long algebraic form used by perft and engine protocols, e.g. "e2e4" or "e7e8q"
+pub fn move_name(original_index: usize, index: usize, promotion: Option<Pieces>) -> String {
+    let promotion = match promotion {
+        Some(Pieces::BISH) => "b",
+        Some(Pieces::KNIG) => "n",
+        Some(Pieces::ROOK) => "r",
+        Some(Pieces::QUEE) => "q",
+        _ => "",
+    };
+
+    format!("{}{}{}", square_name(original_index), square_name(index), promotion)
 }
 
-pub fn push_if_inbounds(legal_moves: &mut Vec<usize>, file: i32, rank: i32) {
-    if in_bounds(file, rank) {
-        legal_moves.push(index_of(file.clone(), rank.clone()));
+pub fn parse_move_name(name: &str) -> Option<(usize, usize, Option<Pieces>)> {
+    if !name.is_ascii() || name.len() < 4 || name.len() > 5 {
+        return None;
     }
+    let original_index = square_from_name(&name[0..2])?;
+    let index = square_from_name(&name[2..4])?;
+    let promotion = match &name[4..] {
+        "" => None,
+        "b" => Some(Pieces::BISH),
+        "n" => Some(Pieces::KNIG),
+        "r" => Some(Pieces::ROOK),
+        "q" => Some(Pieces::QUEE),
+        _ => return None,
+    };
+
+    Some((original_index, index, promotion))
+}
+
+pub fn in_bounds(file: i32, rank: i32) -> bool {
+    (0..8).contains(&file) && (0..8).contains(&rank)
 }
 
-pub fn generate_pseudolegal_moves(piece: i8, starting_index: i32, board: &Vec<i8>, is_bit_board_calc: bool, en_passant_index: usize) -> Vec<usize> {
-    let mut legal_moves: Vec<usize> = Vec::new();
-    let piece_type = piece & 7;
-    let piece_is_white = is_white(piece);
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position {
+    pub board: Vec<i8>,
+    // the squares of every piece, indexed by its code color bit included, and of every side, indexed by
+    // whether it is white; change the board through set_square so they stay in step with it
+    pub pieces: [u64; 16],
+    pub colors: [u64; 2],
+    pub is_white_turn: bool,
+    pub castling_rights: CastlingRights,
+    pub en_passant: Option<usize>,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+    // Zobrist key, updated by play; after changing the fields directly call compute_key
+    pub key: u64,
+    // keys of the positions before every move played, the last move's at the end
+    pub history: Vec<u64>,
+    // castling is then played by moving the king onto its rook, as UCI_Chess960 and X-FEN expect
+    pub chess960: bool,
+}
 
-    if piece_type == Pieces::PAWN as i8 {
-        let mut starting_rank = 1;
-        let mut dir = Directions::DOWN as i32;
-        if piece_is_white {
-            starting_rank = 6;
-            dir = Directions::UP as i32;
+impl Position {
+    pub fn new(board: Vec<i8>) -> Position {
+        let mut position = Position {
+            board: vec![0; 64],
+            pieces: [0; 16],
+            colors: [0; 2],
+            is_white_turn: true,
+            castling_rights: CastlingRights::all(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            key: 0,
+            history: Vec::new(),
+            chess960: false,
+        };
+        for (index, piece) in board.into_iter().enumerate() {
+            position.set_square(index, piece);
         }
-        let x = file_of(starting_index as usize);
-        let y = rank_of(starting_index as usize);
-        let mut y_move = y + xy_dir(dir);
-
-        if is_empty(board, x, y_move) && !is_bit_board_calc {
-            push_if_inbounds(&mut legal_moves, x, y_move);
+        position.key = position.compute_key();
+        position
+    }
 
-            if starting_rank == y && is_empty(board, x, y + 2 * xy_dir(dir)){
-                y_move = y + (2 * xy_dir(dir));
-                push_if_inbounds(&mut legal_moves, x, y_move);
+    // the en passant square only counts when a pawn could actually take there
+    fn en_passant_key(&self) -> u64 {
+        match self.en_passant {
+            Some(index) if PAWN_ATTACKS[!self.is_white_turn as usize][index] & self.pieces_of(Pieces::PAWN, self.is_white_turn) != 0 => {
+                en_passant_key(file_of(index))
             }
+            _ => 0,
         }
-        for x_dir in vec![-1, 1] {
-            if is_enemy(board, piece_is_white, x + x_dir, y + xy_dir(dir)) 
-            || en_passant_index == index_of(x + x_dir, y + xy_dir(dir)) || is_bit_board_calc {
-                push_if_inbounds(&mut legal_moves, x + x_dir, y + xy_dir(dir));
-            }
+    }
+
+    // the key worked out from scratch rather than move by move
+    pub fn compute_key(&self) -> u64 {
+        let mut key = castling_key(&self.castling_rights) ^ self.en_passant_key();
+
+        for index in Squares(self.occupied()) {
+            key ^= piece_key(self.board[index], index);
         }
+        if !self.is_white_turn {
+            key ^= BLACK_TO_MOVE;
+        }
+        key
     }
 
-    if is_sliding_piece(piece_type) {
-        let mut slide_dir: Vec<i32> = Vec::new();
+    // how many times the position occurred before with the same side to move, looking back no further
+    // than the last capture or pawn move
+    pub fn repetitions(&self) -> usize {
+        self.history
+            .iter()
+            .rev()
+            .take(self.halfmove_clock as usize)
+            .skip(1)
+            .step_by(2)
+            .filter(|key| **key == self.key)
+            .count()
+    }
 
-        if piece_type == Pieces::BISH as i8 || piece_type == Pieces::QUEE as i8 {
-            slide_dir.push(Directions::UP as i32 + Directions::RIGHT as i32);
-            slide_dir.push(Directions::UP as i32 + Directions::LEFT as i32);
-            slide_dir.push(Directions::DOWN as i32 + Directions::RIGHT as i32);
-            slide_dir.push(Directions::DOWN as i32 + Directions::LEFT as i32);
+    // puts the piece on the square, 0 emptying it
+    pub fn set_square(&mut self, index: usize, piece: i8) {
+        let old = self.board[index];
+        if old != 0 {
+            self.pieces[old as usize] &= !bit(index);
+            self.colors[is_white(old) as usize] &= !bit(index);
+            self.key ^= piece_key(old, index);
         }
-        if piece_type == Pieces::ROOK as i8 || piece_type == Pieces::QUEE as i8 {
-            slide_dir.push(Directions::UP as i32);
-            slide_dir.push(Directions::LEFT as i32);
-            slide_dir.push(Directions::RIGHT as i32);
-            slide_dir.push(Directions::DOWN as i32);
+        if piece != 0 {
+            self.pieces[piece as usize] |= bit(index);
+            self.colors[is_white(piece) as usize] |= bit(index);
+            self.key ^= piece_key(piece, index);
         }
+        self.board[index] = piece;
+    }
 
-        let starting_rank = rank_of(starting_index as usize);
-        let starting_file = file_of(starting_index as usize);
+    pub fn pieces_of(&self, piece_type: Pieces, white: bool) -> u64 {
+        self.pieces[piece_type as usize + if white { 8 } else { 0 }]
+    }
 
-        for dir in slide_dir {
-            let dir_rank = if dir.abs() == 1 { 0 } else if dir < -6 { -1 } else { 1 };
-            let dir_file = if dir.abs() == 8 { 0 } else if dir.abs() == 7 || dir == -1 { -1 } else { 1 };
-            let mut rank = dir_rank + starting_rank;
-            let mut file = dir_file + starting_file;
+    pub fn occupied(&self) -> u64 {
+        self.colors[0] | self.colors[1]
+    }
 
-            'slide: loop {
-                if !in_bounds(file, rank) {
-                    break;
-                }
-                if is_empty(board, file, rank) {
-                    legal_moves.push(index_of(file, rank));
-                } else {
-                    if is_enemy(board, piece_is_white, file, rank) || is_bit_board_calc {
-                        legal_moves.push(index_of(file, rank));
-                    }
-                    break 'slide;
-                }
-                rank += dir_rank;
-                file += dir_file;
-            }
-        }
+    // whether any piece of the given side attacks the square, looking from the square outwards
+    pub fn is_attacked(&self, index: usize, by_white: bool) -> bool {
+        let occupied = self.occupied();
+        let diagonal = self.pieces_of(Pieces::BISH, by_white) | self.pieces_of(Pieces::QUEE, by_white);
+        let straight = self.pieces_of(Pieces::ROOK, by_white) | self.pieces_of(Pieces::QUEE, by_white);
+
+        PAWN_ATTACKS[!by_white as usize][index] & self.pieces_of(Pieces::PAWN, by_white) != 0
+        || KNIGHT_ATTACKS[index] & self.pieces_of(Pieces::KNIG, by_white) != 0
+        || KING_ATTACKS[index] & self.pieces_of(Pieces::KING, by_white) != 0
+        || bishop_attacks(index, occupied) & diagonal != 0
+        || rook_attacks(index, occupied) & straight != 0
     }
 
-    if piece_type == Pieces::KNIG as i8 {
-        let hops_rank = vec![-2, -1, 1, 2];
-        let hops_file = vec![-1, -2, -2, -1];
+    // every square attacked by the given side
+    pub fn attacks(&self, white: bool) -> u64 {
+        let occupied = self.occupied();
 
-        let start_rank = rank_of(starting_index as usize);
-        let start_file = file_of(starting_index as usize);
+        Squares(self.colors[white as usize]).fold(0, |attacks, index| attacks | piece_attacks(self.board[index], index, occupied))
+    }
 
-        for i in [-1, 1] {
-            for k in 0..4 {
-                let rank = start_rank + hops_rank[k];
-                let file = start_file + hops_file[k] * i;
+    // squares attacked by the side that is not to move
+    pub fn attacked_squares(&self) -> u64 {
+        self.attacks(!self.is_white_turn)
+    }
 
-                if is_empty_or_enemy(board.to_vec(), piece_is_white, file, rank) {
-                    push_if_inbounds(&mut legal_moves, file, rank);
-                }
-            }
+    // where the piece on the square can go if its own king's safety is ignored, castling left out
+    pub fn pseudo_legal_targets(&self, from: usize) -> u64 {
+        let piece = self.board[from];
+        if piece == 0 {
+            return 0;
         }
-    }
+        let white = is_white(piece);
+        let occupied = self.occupied();
 
-    if piece_type == Pieces::KING as i8 {
-        let dirs = [
-            Directions::UP as i32,
-            Directions::LEFT as i32,
-            Directions::DOWN as i32,
-            Directions::RIGHT as i32,
-            Directions::UP as i32 + Directions::LEFT as i32,
-            Directions::UP as i32 + Directions::RIGHT as i32,
-            Directions::DOWN as i32 + Directions::LEFT as i32,
-            Directions::DOWN as i32 + Directions::RIGHT as i32
-        ];
-        
-        for dir in dirs {
-            let dir_rank = if dir.abs() == 1 { 0 } else if dir < -6 { -1 } else { 1 };
-            let dir_file = if dir.abs() == 8 { 0 } else if dir.abs() == 7 || dir == -1 { -1 } else { 1 };
-            let rank = dir_rank + rank_of(starting_index as usize);
-            let file = dir_file + file_of(starting_index as usize);
-
-            if is_empty_or_enemy(board.to_vec(), piece_is_white, file, rank) {
-                push_if_inbounds(&mut legal_moves, file, rank);
-            } else if is_bit_board_calc && !is_empty_or_enemy(board.to_vec(), piece_is_white, file, rank) {
-                push_if_inbounds(&mut legal_moves, file, rank);
+        if piece & 7 != Pieces::PAWN as i8 {
+            return piece_attacks(piece, from, occupied) & !self.colors[white as usize];
+        }
+
+        let enemies = self.colors[!white as usize] | self.en_passant.map_or(0, bit);
+        let mut targets = PAWN_ATTACKS[white as usize][from] & enemies;
+
+        let (dir, starting_rank) = if white { (-1, 6) } else { (1, 1) };
+        let (file, rank) = (file_of(from), rank_of(from));
+        if in_bounds(file, rank + dir) && occupied & bit(index_of(file, rank + dir)) == 0 {
+            targets |= bit(index_of(file, rank + dir));
+
+            if rank == starting_rank && occupied & bit(index_of(file, rank + 2 * dir)) == 0 {
+                targets |= bit(index_of(file, rank + 2 * dir));
             }
         }
+        targets
     }
-    legal_moves
-}
 
-pub fn generate_bit_board(board: &Vec<i8>, is_white_turn: bool) -> u64 {
-    let is_white_bit_board = !is_white_turn;
-    let mut bitboard: u64 = 0;
+    // where the king and the rook land when castling on a wing, with the square the rook starts on:
+    // (king to, rook from, rook to)
+    pub fn castling_squares(&self, white: bool, king_side: bool) -> (usize, usize, usize) {
+        let rank = back_rank(white);
+        let rook_from = index_of(self.castling_rights.rook_file(white, king_side), rank);
 
-    let mut i = 0;
-    for piece in board.to_vec() {
-        if piece != 0 && is_white(piece) == is_white_bit_board {
-            for attacked_square in generate_pseudolegal_moves(piece, i, &board.to_vec(), true, 999) {
-                bitboard |= 2u64.pow(attacked_square as u32);
-            }
+        if king_side {
+            (index_of(6, rank), rook_from, index_of(5, rank))
+        } else {
+            (index_of(2, rank), rook_from, index_of(3, rank))
         }
-        i += 1;
     }
 
-    bitboard
-}
+    // the square a castling king move goes to: its landing square, or its own rook in Chess960
+    fn castling_target(&self, white: bool, king_side: bool) -> Option<usize> {
+        let (king_to, rook_from, _) = self.castling_squares(white, king_side);
+        let king = self.pieces_of(Pieces::KING, white);
 
-pub fn generate_legal_moves(piece: i8, starting_index: i32, board: &Vec<i8>, bitboard: u64, en_passant_index: usize) -> Vec<usize> {
-    let mut pseudo_legal_moves = generate_pseudolegal_moves(piece, starting_index, board, false, en_passant_index);
-
-    let king_index = board.iter()
-        .position( |id| 
-            id & 7 == Pieces::KING as i8 
-            && !is_different_color(*id, piece))
-        .unwrap_or(999); // if no king on board then it is in hand
-    
-    if king_index != 999 && 2u64.pow(king_index as u32) & bitboard != 0 {
-        let mut i = 0;
-
-        'checker: loop {
-            if i >= pseudo_legal_moves.len() {
-                break 'checker;
-            }
+        // outside Chess960 the king has to be on the e-file, other positions are not set up as FEN would write them
+        let king_in_place = king & bit(index_of(4, back_rank(white))) != 0
+            || self.chess960 && king & rank_span(index_of(0, back_rank(white)), index_of(7, back_rank(white))) != 0;
 
-            let current_move = pseudo_legal_moves[i];
-            let mut board_after = board.clone();
-            board_after[current_move] = piece;
+        if !self.castling_rights.can_castle(white, king_side)
+        || !king_in_place
+        || self.pieces_of(Pieces::ROOK, white) & bit(rook_from) == 0 {
+            return None;
+        }
+        Some(if self.chess960 { rook_from } else { king_to })
+    }
 
-            let bboard = generate_bit_board(&board_after, is_white(piece));
-            if 2u64.pow(king_index as u32) & bboard != 0 {
-                pseudo_legal_moves.remove(i);
-            } else {
-                i += 1;
+    fn castling_targets(&self, from: usize) -> u64 {
+        let piece = self.board[from];
+        let white = is_white(piece);
+        if piece & 7 != Pieces::KING as i8 {
+            return 0;
+        }
+
+        let mut targets = 0;
+        for king_side in [true, false] {
+            let Some(target) = self.castling_target(white, king_side) else {
+                continue;
+            };
+            let (king_to, rook_from, rook_to) = self.castling_squares(white, king_side);
+            let king_path = rank_span(from, king_to);
+
+            // the squares both pieces cross or land on hold nothing but the two of them, and the king
+            // is not in check, does not pass through an attacked square and does not land on one
+            let others = self.occupied() & !bit(from) & !bit(rook_from);
+            if (king_path | rank_span(rook_from, rook_to)) & others != 0
+            || king_path & self.attacks(!white) != 0 {
+                continue;
             }
+            targets |= bit(target);
         }
+        targets
     }
 
-    // if king in hand
-    if piece & 7 == Pieces::KING as i8 {
-        // so legal moves we are looking at are the king moves
+    // the wing a king move castles to, None for any other move
+    pub fn castling_wing(&self, from: usize, to: usize) -> Option<bool> {
+        let piece = self.board[from];
+        if piece & 7 != Pieces::KING as i8 {
+            return None;
+        }
 
-        let mut i = 0;
-        'checker: loop {
-            if i >= pseudo_legal_moves.len() {
-                break 'checker; 
-            }
-            let current_move = pseudo_legal_moves[i];
+        [true, false].into_iter().find(|king_side| self.castling_target(is_white(piece), *king_side) == Some(to))
+    }
+
+    // plays the move on the scratch copy and takes it back, so pins, discovered checks
+    // and the pawn removed by en passant are all accounted for
+    fn keeps_king_safe(scratch: &mut Position, mv: Move) -> bool {
+        let undo = scratch.play(mv);
+        let safe = !scratch.king_in_check(is_white(mv.piece));
+        scratch.unmake_move(&undo);
 
-            // if current move in bit board, remove it
-            if 2u64.pow(current_move as u32) & bitboard != 0 {
-                pseudo_legal_moves.remove(i);
-            } else {
-                i += 1;
+        safe
+    }
+
+    fn legal_targets(&self, scratch: &mut Position, from: usize) -> impl Iterator<Item = usize> {
+        Squares(self.pseudo_legal_targets(from) | self.castling_targets(from))
+            .filter(move |to| Position::keeps_king_safe(scratch, self.new_move(from, *to, None)))
+    }
+
+    pub fn legal_moves(&self, starting_index: usize) -> Vec<usize> {
+        self.legal_targets(&mut self.clone(), starting_index).collect()
+    }
+
+    // describes moving the piece on from to the square to, whether it is legal or not
+    pub fn new_move(&self, from: usize, to: usize, promotion: Option<Pieces>) -> Move {
+        let piece = self.board[from];
+        let is_pawn = piece & 7 == Pieces::PAWN as i8;
+
+        let (captured, flag) = if is_pawn && Some(to) == self.en_passant {
+            (Pieces::PAWN as i8 + (self.board[from] & 8 ^ 8), MoveFlag::EnPassant)
+        } else if is_pawn && (rank_of(from) - rank_of(to)).abs() == 2 {
+            (0, MoveFlag::DoublePush)
+        } else if self.castling_wing(from, to).is_some() {
+            (0, MoveFlag::Castle)
+        } else {
+            (self.board[to], MoveFlag::Normal)
+        };
+
+        Move { from, to, piece, captured, promotion, flag }
+    }
+
+    // every legal move of the side to move, a promotion appearing once per piece it can become
+    pub fn legal_move_list(&self) -> Vec<Move> {
+        let mut moves: Vec<Move> = Vec::new();
+        let mut scratch = self.clone();
+
+        for from in Squares(self.colors[self.is_white_turn as usize]) {
+            let is_pawn = self.board[from] & 7 == Pieces::PAWN as i8;
+
+            for to in self.legal_targets(&mut scratch, from) {
+                if is_pawn && (rank_of(to) == 0 || rank_of(to) == 7) {
+                    for promotion in PROMOTIONS {
+                        moves.push(self.new_move(from, to, Some(promotion)));
+                    }
+                } else {
+                    moves.push(self.new_move(from, to, None));
+                }
             }
         }
+        moves
+    }
+
+    // the legal move going from and to these squares, a pawn reaching the last rank needing its promotion
+    pub fn find_move(&self, from: usize, to: usize, promotion: Option<Pieces>) -> Option<Move> {
+        self.legal_move_list()
+            .into_iter()
+            .find(|legal| legal.from == from && legal.to == to && legal.promotion == promotion)
     }
-    
-    pseudo_legal_moves
-}
 
-pub fn generate_all_legal_moves(white_turn: bool, board: &Vec<i8>, bitboard: u64, en_passant_index: usize) -> HashMap<usize, Vec<usize>>{
-    let mut legal_moves: HashMap<usize, Vec<usize>> = HashMap::new();
-    
-    let mut i = 0;
-    for piece in board.to_vec() {
-        if piece != 0 && is_white(piece) == white_turn {
-            legal_moves.insert(i, generate_legal_moves(piece, i as i32, board, bitboard, en_passant_index));
+    pub fn king_index(&self, white: bool) -> Option<usize> {
+        Squares(self.pieces_of(Pieces::KING, white)).next()
+    }
+
+    pub fn king_in_check(&self, white: bool) -> bool {
+        match self.king_index(white) {
+            Some(index) => self.is_attacked(index, !white),
+            None => false,
         }
-        i += 1;
     }
-    legal_moves
-}
 
-pub fn checkmate(white_turn: bool, board: &Vec<i8>, bitboard: u64, en_passant_index: usize) -> bool {
-    let legal_moves = generate_all_legal_moves(!white_turn, board, bitboard, en_passant_index);
+    pub fn in_check(&self) -> bool {
+        self.king_in_check(self.is_white_turn)
+    }
+
+    pub fn has_legal_moves(&self) -> bool {
+        let mut scratch = self.clone();
 
-    for piece in legal_moves.clone().keys() {
-        if legal_moves[piece].len() != 0 {
+        Squares(self.colors[self.is_white_turn as usize]).any(|from| self.legal_targets(&mut scratch, from).next().is_some())
+    }
+
+    // the game is over once the side to move is out of moves, lost if its king is attacked and drawn otherwise,
+    // or when mate became impossible, a position comes up for the fifth time or seventy-five moves pass
+    // without a capture or pawn move
+    pub fn outcome(&self) -> Option<Outcome> {
+        if !self.has_legal_moves() {
+            if self.in_check() {
+                return Some(Outcome::Checkmate { white_wins: !self.is_white_turn });
+            }
+            return Some(Outcome::Stalemate);
+        }
+
+        if self.insufficient_material() {
+            Some(Outcome::InsufficientMaterial)
+        } else if self.repetitions() >= 4 {
+            Some(Outcome::FivefoldRepetition)
+        } else if self.halfmove_clock >= 150 {
+            Some(Outcome::SeventyFiveMoveRule)
+        } else {
+            None
+        }
+    }
+
+    // no mate is possible with nothing but kings and either a single knight or bishop, or bishops
+    // all standing on squares of the same color
+    pub fn insufficient_material(&self) -> bool {
+        let heavy = [Pieces::PAWN, Pieces::ROOK, Pieces::QUEE]
+            .iter()
+            .any(|piece| self.pieces_of(*piece, true) | self.pieces_of(*piece, false) != 0);
+        if heavy {
             return false;
         }
+
+        let knights = self.pieces_of(Pieces::KNIG, true) | self.pieces_of(Pieces::KNIG, false);
+        let bishops = self.pieces_of(Pieces::BISH, true) | self.pieces_of(Pieces::BISH, false);
+
+        (knights | bishops).count_ones() <= 1
+        || knights == 0 && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0)
     }
 
-    true
-}
+    // whether the side could mate by any series of legal moves, however unlikely: a lone king never can,
+    // nor a lone knight against nothing but queens, nor bishops while every bishop on the board
+    // stands on one color and there are no pawns or knights to block the king in
+    pub fn has_mating_material(&self, white: bool) -> bool {
+        if [Pieces::PAWN, Pieces::ROOK, Pieces::QUEE].iter().any(|piece| self.pieces_of(*piece, white) != 0) {
+            return true;
+        }
+
+        if self.pieces_of(Pieces::KNIG, white) != 0 {
+            let blockers = self.colors[!white as usize] & !self.pieces_of(Pieces::KING, !white) & !self.pieces_of(Pieces::QUEE, !white);
+            return self.colors[white as usize].count_ones() > 2 || blockers != 0;
+        }
 
-pub fn make_move(is_white_turn: bool, board: &mut Vec<i8>, legal_piece_moves: &mut Vec<usize>, bitboard: u64, piece: i8, original_index: usize, index: usize, en_passant_index: &mut usize, can_castle: bool) -> MoveResult {
-    let legal = legal_piece_moves.contains(&index);
+        if self.pieces_of(Pieces::BISH, white) != 0 {
+            let bishops = self.pieces_of(Pieces::BISH, true) | self.pieces_of(Pieces::BISH, false);
+            let blockers = [Pieces::PAWN, Pieces::KNIG].iter().any(|piece| self.pieces_of(*piece, !white) != 0);
+            return (bishops & LIGHT_SQUARES != 0 && bishops & !LIGHT_SQUARES != 0) || blockers;
+        }
+        false
+    }
 
-    if is_white_turn == is_white(piece)
-    && legal 
-    && index != original_index{
-        if piece & 7 == Pieces::PAWN as i8 {
-            if index == *en_passant_index {
-                board[index_of(file_of(*en_passant_index), rank_of(original_index))] = 0;
+    // a draw either player may claim in a game that is not over yet
+    pub fn draw_claim(&self) -> Option<Outcome> {
+        let claim = if self.repetitions() >= 2 {
+            Outcome::ThreefoldRepetition
+        } else if self.halfmove_clock >= 100 {
+            Outcome::FiftyMoveRule
+        } else {
+            return None;
+        };
+
+        if self.outcome().is_some() {
+            return None;
+        }
+        Some(claim)
+    }
+
+    // applies a move without checking it, returning what unmake_move needs to take it back
+    pub fn play(&mut self, mv: Move) -> Undo {
+        let undo = Undo {
+            mv,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+        };
+        self.history.push(self.key);
+        self.key ^= castling_key(&self.castling_rights) ^ self.en_passant_key();
+
+        self.en_passant = None;
+        self.castling_rights.update(mv.piece, mv.from, mv.to);
+
+        let mut landing = mv.to;
+        self.set_square(mv.from, 0);
+        match mv.flag {
+            MoveFlag::EnPassant => self.set_square(index_of(file_of(mv.to), rank_of(mv.from)), 0),
+            MoveFlag::DoublePush => self.en_passant = Some((mv.from + mv.to) / 2),
+            MoveFlag::Castle => {
+                // both pieces are lifted first, in Chess960 either may land where the other stood
+                let (king_to, rook_from, rook_to) = self.castling_squares(is_white(mv.piece), mv.to > mv.from);
+                self.set_square(rook_from, 0);
+                self.set_square(rook_to, Pieces::ROOK as i8 + (mv.piece & 8));
+                landing = king_to;
             }
-            *en_passant_index == 999;
-            if is_white(piece) && rank_of(original_index) - 2 == rank_of(index) {
-                *en_passant_index = original_index - 8;
-            } else if !is_white(piece) && rank_of(original_index) + 2 == rank_of(index) {
-                *en_passant_index = original_index + 8;
+            MoveFlag::Normal => (),
+        }
+        match mv.promotion {
+            Some(promotion) => self.set_square(landing, promotion as i8 + (mv.piece & 8)),
+            None => self.set_square(landing, mv.piece),
+        }
+
+        if mv.piece & 7 == Pieces::PAWN as i8 || mv.is_capture() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if !self.is_white_turn {
+            self.fullmove_number += 1;
+        }
+        self.is_white_turn = !self.is_white_turn;
+        self.key ^= BLACK_TO_MOVE ^ castling_key(&self.castling_rights) ^ self.en_passant_key();
+
+        undo
+    }
+
+    // restores the position as it was before the move of the undo record was played
+    pub fn unmake_move(&mut self, undo: &Undo) {
+        let mv = undo.mv;
+
+        match mv.flag {
+            MoveFlag::EnPassant => {
+                self.set_square(mv.to, 0);
+                self.set_square(index_of(file_of(mv.to), rank_of(mv.from)), mv.captured);
             }
-            if rank_of(index) == 0 || rank_of(index) == 7 {
-                return MoveResult::Promotion(index);
+            MoveFlag::Castle => {
+                let (king_to, rook_from, rook_to) = self.castling_squares(is_white(mv.piece), mv.to > mv.from);
+                self.set_square(king_to, 0);
+                self.set_square(rook_to, 0);
+                self.set_square(rook_from, Pieces::ROOK as i8 + (mv.piece & 8));
             }
+            _ => self.set_square(mv.to, mv.captured),
         }
-        board[index] = piece;
-        if checkmate(is_white_turn, &board, bitboard, *en_passant_index) {
-            return MoveResult::Checkmate;
-        }
-    } else {
-        board[original_index] = piece;
-        return MoveResult::Fail;
+        self.set_square(mv.from, mv.piece);
+
+        self.castling_rights = undo.castling_rights;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
+        self.is_white_turn = !self.is_white_turn;
+        self.key = match self.history.pop() {
+            Some(key) => key,
+            None => self.compute_key(),
+        };
     }
 
-    MoveResult::Move
-}
\ No newline at end of file
+    // a move can be several things at once, e.g. a capture that promotes and gives check
+    pub fn make_move(&mut self, mv: Move) -> Vec<MoveResult> {
+        if !self.legal_move_list().contains(&mv) {
+            return vec![MoveResult::Fail];
+        }
+
+        let mut results: Vec<MoveResult> = Vec::new();
+
+        self.play(mv);
+
+        if let Some(captured) = Pieces::from_piece(mv.captured) {
+            results.push(MoveResult::Capture(captured));
+        }
+        if mv.flag == MoveFlag::Castle {
+            results.push(MoveResult::Castle);
+        }
+        if let Some(promotion) = mv.promotion {
+            results.push(MoveResult::Promotion(promotion));
+        }
+
+        if self.in_check() {
+            results.push(MoveResult::Check);
+        }
+        if let Some(outcome) = self.outcome() {
+            results.push(MoveResult::GameOver(outcome));
+        } else if let Some(claim) = self.draw_claim() {
+            results.push(MoveResult::DrawClaimable(claim));
+        }
+
+        if results.is_empty() {
+            results.push(MoveResult::Move);
+        }
+        results
+    }
+}
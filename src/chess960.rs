@@ -0,0 +1,56 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+use crate::chess::{Pieces, Position};
+use crate::fen::{piece_to_letter, translate_fen};
+
+pub const START_POSITIONS: u32 = 960;
+// the standard starting position in Scharnagl's numbering
+pub const STANDARD_START: u32 = 518;
+
+// the pieces of white's back rank from the a-file to the h-file in starting position n,
+// numbered from 0 as Scharnagl did; None past the last one
+pub fn back_rank(n: u32) -> Option<[Pieces; 8]> {
+    if n >= START_POSITIONS {
+        return None;
+    }
+    let mut rank: [Option<Pieces>; 8] = [None; 8];
+
+    // the light squared bishop takes b, d, f or h, the dark squared one a, c, e or g
+    let (n, light) = (n / 4, n % 4);
+    rank[2 * light as usize + 1] = Some(Pieces::BISH);
+    let (n, dark) = (n / 4, n % 4);
+    rank[2 * dark as usize] = Some(Pieces::BISH);
+
+    // then the queen and both knights go on the squares left, counted from the a-file
+    let (n, queen) = (n / 6, n % 6);
+    let knights = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)][n as usize];
+
+    let empty = |rank: &[Option<Pieces>; 8]| -> Vec<usize> { (0..8).filter(|file| rank[*file].is_none()).collect() };
+    rank[empty(&rank)[queen as usize]] = Some(Pieces::QUEE);
+    let free = empty(&rank);
+    rank[free[knights.0]] = Some(Pieces::KNIG);
+    rank[free[knights.1]] = Some(Pieces::KNIG);
+
+    // the king always ends up between the rooks
+    for (file, piece) in empty(&rank).into_iter().zip([Pieces::ROOK, Pieces::KING, Pieces::ROOK]) {
+        rank[file] = Some(piece);
+    }
+
+    Some(rank.map(|piece| piece.unwrap()))
+}
+
+// starting position n, black mirroring white and both sides free to castle with either rook
+pub fn start_position(n: u32) -> Option<Position> {
+    let white: String = back_rank(n)?.iter().map(|piece| piece_to_letter(*piece as i8 + 8)).collect();
+    let fen = format!("{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1", white.to_ascii_lowercase(), white);
+
+    let mut position = translate_fen(&fen).unwrap();
+    position.chess960 = true;
+    Some(position)
+}
+
+// the std hasher is seeded at random, which is all the randomness a starting position needs
+pub fn random_start() -> u32 {
+    (RandomState::new().build_hasher().finish() % START_POSITIONS as u64) as u32
+}
@@ -0,0 +1,133 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::{bot, chess960, fen};
+use crate::clock::{parse_time_control, Stage};
+
+pub const USAGE: &str = "usage: ChessRust [--fen <fen>] [--bot white|black|none] [--bot-depth <plies>] [--bot-time <ms>] [--engine <path>] [--debug-attacks] [--pgn <file>] [--save-pgn <file>] [--chess960 <n>|random] [--clock <time control>]
+       ChessRust perft <depth> [--fen <fen>]";
+
+#[derive(Debug)]
+pub enum Command {
+    Play(Options),
+    Perft { depth: u32, fen: String },
+}
+
+#[derive(Debug)]
+pub struct Options {
+    pub fen: String,
+    // Some(true) when the bot plays white, None when both sides are human
+    pub bot: Option<bool>,
+    pub bot_limits: bot::Limits,
+    // a UCI engine executable playing in place of the built-in bot
+    pub engine: Option<String>,
+    pub debug_attacks: bool,
+    pub pgn: Option<String>,
+    // where the game is written when it ends, Ctrl+S saves it there or to game.pgn
+    pub save_pgn: Option<String>,
+    // the Chess960 starting position to play from instead of the FEN
+    pub chess960: Option<u32>,
+    // the time control both sides play under, no clock when None
+    pub clock: Option<Vec<Stage>>,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            fen: fen::STARTING_FEN.to_string(),
+            bot: None,
+            bot_limits: bot::Limits { depth: None, time: Some(Duration::from_secs(1)) },
+            engine: None,
+            debug_attacks: false,
+            pgn: None,
+            save_pgn: None,
+            chess960: None,
+            clock: None,
+        }
+    }
+}
+
+fn value_of(flag: &str, args: &mut impl Iterator<Item = String>) -> Result<String, String> {
+    args.next().ok_or(format!("{} expects a value", flag))
+}
+
+pub fn parse_args(args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut args = args.peekable();
+
+    if args.peek().map(|arg| arg.as_str()) == Some("perft") {
+        args.next();
+        return parse_perft_args(args);
+    }
+    Ok(Command::Play(parse_play_args(args)?))
+}
+
+fn parse_perft_args(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let depth = args.next().ok_or("perft expects a depth")?;
+    let depth = depth.parse().map_err(|_| format!("invalid perft depth '{}'", depth))?;
+    let mut fen = fen::STARTING_FEN.to_string();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--fen" => fen = value_of(&arg, &mut args)?,
+            _ => return Err(format!("unknown argument '{}'", arg)),
+        }
+    }
+
+    Ok(Command::Perft { depth, fen })
+}
+
+// values out of the type's range are rejected along with anything that is not a number
+fn parse_number<T: FromStr>(flag: &str, args: &mut impl Iterator<Item = String>) -> Result<T, String> {
+    let value = value_of(flag, args)?;
+    value.parse().map_err(|_| format!("{} expects a number, found '{}'", flag, value))
+}
+
+fn parse_play_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut bot_depth: Option<u32> = None;
+    let mut bot_time: Option<Duration> = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--fen" => options.fen = value_of(&arg, &mut args)?,
+            "--bot" => {
+                options.bot = match value_of(&arg, &mut args)?.as_str() {
+                    "white" => Some(true),
+                    "black" => Some(false),
+                    "none" => None,
+                    side => return Err(format!("--bot expects white, black or none, found '{}'", side)),
+                }
+            }
+            "--bot-depth" => bot_depth = Some(parse_number(&arg, &mut args)?),
+            "--bot-time" => bot_time = Some(Duration::from_millis(parse_number(&arg, &mut args)?)),
+            "--engine" => options.engine = Some(value_of(&arg, &mut args)?),
+            "--debug-attacks" => options.debug_attacks = true,
+            "--pgn" => options.pgn = Some(value_of(&arg, &mut args)?),
+            "--save-pgn" => options.save_pgn = Some(value_of(&arg, &mut args)?),
+            "--chess960" => {
+                options.chess960 = match value_of(&arg, &mut args)?.as_str() {
+                    "random" => Some(chess960::random_start()),
+                    n => match n.parse() {
+                        Ok(n) if n < chess960::START_POSITIONS => Some(n),
+                        _ => return Err(format!("--chess960 expects random or a number below 960, found '{}'", n)),
+                    },
+                }
+            }
+            "--clock" => {
+                let control = value_of(&arg, &mut args)?;
+                options.clock = Some(parse_time_control(&control).ok_or(format!(
+                    "--clock expects a time control such as 5, 3+2, 5d3 or 40/90+30:30+30, found '{}'",
+                    control
+                ))?);
+            }
+            _ => return Err(format!("unknown argument '{}'", arg)),
+        }
+    }
+
+    // a depth on its own searches without a clock, otherwise the default time limit stays
+    if bot_depth.is_some() || bot_time.is_some() {
+        options.bot_limits = bot::Limits { depth: bot_depth, time: bot_time };
+    }
+
+    Ok(options)
+}
@@ -0,0 +1,178 @@
+use std::time::{Duration, Instant};
+
+use crate::chess::{Outcome, Position};
+use crate::game::Game;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Increment {
+    // added after every move
+    Fischer(Duration),
+    // the time a move took is given back, up to the delay
+    Bronstein(Duration),
+}
+
+// a period of the time control, the last one lasting for the rest of the game
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stage {
+    // moves to make before the next stage starts, None for sudden death
+    pub moves: Option<u32>,
+    pub time: Duration,
+    pub increment: Option<Increment>,
+}
+
+fn parse_number(text: &str) -> Option<f64> {
+    text.parse().ok().filter(|number: &f64| number.is_finite() && *number >= 0.0)
+}
+
+// "[moves/]minutes[+seconds|dseconds]"
+fn parse_stage(text: &str) -> Option<Stage> {
+    let (moves, text) = match text.split_once('/') {
+        Some((moves, text)) => (Some(moves.parse().ok().filter(|moves| *moves > 0)?), text),
+        None => (None, text),
+    };
+
+    let (minutes, increment) = if let Some((minutes, seconds)) = text.split_once('+') {
+        (minutes, Some(Increment::Fischer(Duration::from_secs_f64(parse_number(seconds)?))))
+    } else if let Some((minutes, seconds)) = text.split_once('d') {
+        (minutes, Some(Increment::Bronstein(Duration::from_secs_f64(parse_number(seconds)?))))
+    } else {
+        (text, None)
+    };
+    let minutes = parse_number(minutes).filter(|minutes| *minutes > 0.0)?;
+
+    Some(Stage { moves, time: Duration::from_secs_f64(minutes * 60.0), increment })
+}
+
+// stages separated by colons: "5" is five minutes sudden death, "3+2" adds two seconds a move,
+// "5d3" gives back up to three seconds a move and "40/90+30:30+30" is ninety minutes for forty moves
+// then thirty more for the rest of the game, with thirty seconds a move throughout;
+// a last stage with a move count starts over once its moves are made
+pub fn parse_time_control(text: &str) -> Option<Vec<Stage>> {
+    text.split(':').map(parse_stage).collect()
+}
+
+// "4:05" or "1:29:59", rounded up so the clock only reads 0:00 once the flag has fallen
+pub fn clock_text(time: Duration) -> String {
+    let seconds = time.as_millis().div_ceil(1000);
+
+    if seconds >= 3600 {
+        format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+// the side whose flag fell loses, unless the opponent could never mate it
+pub fn flag_fall(position: &Position, white: bool) -> Outcome {
+    if position.has_mating_material(!white) {
+        Outcome::Timeout { white_wins: !white }
+    } else {
+        Outcome::TimeoutVsInsufficientMaterial
+    }
+}
+
+// both players' time, indexed by whether the side is white
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clock {
+    pub stages: Vec<Stage>,
+    pub remaining: [Duration; 2],
+    pub moves: [u32; 2],
+    // the side whose time is running and since when, None while the clock is stopped
+    pub running: Option<(bool, Instant)>,
+}
+
+impl Clock {
+    pub fn new(stages: Vec<Stage>) -> Clock {
+        let time = stages[0].time;
+
+        Clock { stages, remaining: [time; 2], moves: [0; 2], running: None }
+    }
+
+    // the stage the side's next move falls in and how many moves, that one included, are left in it
+    fn stage(&self, white: bool) -> (Stage, Option<u32>) {
+        let mut played = self.moves[white as usize];
+
+        let (last, earlier) = self.stages.split_last().unwrap();
+        for stage in earlier {
+            match stage.moves {
+                Some(moves) if played >= moves => played -= moves,
+                moves => return (*stage, moves.map(|moves| moves - played)),
+            }
+        }
+        (*last, last.moves.map(|moves| moves - played % moves))
+    }
+
+    pub fn moves_to_go(&self, white: bool) -> Option<u32> {
+        self.stage(white).1
+    }
+
+    // the most a move can add back to the side's time
+    pub fn increment(&self, white: bool) -> Duration {
+        match self.stage(white).0.increment {
+            Some(Increment::Fischer(increment) | Increment::Bronstein(increment)) => increment,
+            None => Duration::ZERO,
+        }
+    }
+
+    pub fn start(&mut self, white: bool, now: Instant) {
+        self.running = Some((white, now));
+    }
+
+    pub fn time_left(&self, white: bool, now: Instant) -> Duration {
+        match self.running {
+            Some((side, since)) if side == white => {
+                self.remaining[white as usize].saturating_sub(now.saturating_duration_since(since))
+            }
+            _ => self.remaining[white as usize],
+        }
+    }
+
+    // the side out of time, if the one thinking is
+    pub fn flag_fallen(&self, now: Instant) -> Option<bool> {
+        let (white, _) = self.running?;
+
+        self.time_left(white, now).is_zero().then_some(white)
+    }
+
+    // ends the move of the side thinking: its time goes down, the increment and the time of a new stage
+    // come on top, and the opponent's time starts running
+    pub fn punch(&mut self, now: Instant) {
+        let Some((white, since)) = self.running else {
+            return;
+        };
+        let used = now.saturating_duration_since(since);
+        let (stage, moves_to_go) = self.stage(white);
+
+        let bonus = match stage.increment {
+            Some(Increment::Fischer(increment)) => increment,
+            Some(Increment::Bronstein(delay)) => used.min(delay),
+            None => Duration::ZERO,
+        };
+        let side = white as usize;
+        self.remaining[side] = self.remaining[side].saturating_sub(used) + bonus;
+        self.moves[side] += 1;
+        if moves_to_go == Some(1) {
+            self.remaining[side] += self.stage(white).0.time;
+        }
+
+        self.running = Some((!white, now));
+    }
+
+    // stops the clock and sets it back to where it stood after the game's moves, each side's time
+    // taken from the last move it made
+    pub fn rewind(&mut self, game: &Game) {
+        let time = self.stages[0].time;
+        self.remaining = [time; 2];
+        self.moves = [0; 2];
+        self.running = None;
+
+        for (i, game_move) in game.moves.iter().enumerate() {
+            let side = (game.start.is_white_turn == (i % 2 == 0)) as usize;
+
+            self.moves[side] += 1;
+            if let Some(clock) = game_move.clock {
+                self.remaining[side] = clock;
+            }
+        }
+    }
+}
@@ -1,34 +1,297 @@
-pub fn letter_to_piece(symbol: char) -> i8 {
+use std::fmt;
+
+use crate::chess::{back_rank, index_of, is_white, rank_of, square_from_name, square_name, CastlingRights, Pieces, Position};
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FenError {
+    WrongFieldCount(usize),
+    WrongRankCount(usize),
+    WrongSquareCount { rank: usize, squares: usize },
+    InvalidPiece(char),
+    InvalidSideToMove(String),
+    InvalidCastling(String),
+    InvalidEnPassant(String),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+    MissingKing { white: bool },
+    TooManyKings { white: bool },
+    PawnOnBackRank(usize),
+    OpponentInCheck,
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let color = |white: &bool| if *white { "white" } else { "black" };
+
+        match self {
+            FenError::WrongFieldCount(n) => write!(f, "expected 4 to 6 fields, found {}", n),
+            FenError::WrongRankCount(n) => write!(f, "expected 8 ranks, found {}", n),
+            FenError::WrongSquareCount { rank, squares } => write!(f, "rank {} describes {} squares instead of 8", 8 - rank, squares),
+            FenError::InvalidPiece(c) => write!(f, "'{}' is not a piece", c),
+            FenError::InvalidSideToMove(s) => write!(f, "side to move must be 'w' or 'b', found '{}'", s),
+            FenError::InvalidCastling(s) => write!(f, "invalid castling rights '{}'", s),
+            FenError::InvalidEnPassant(s) => write!(f, "invalid en passant square '{}'", s),
+            FenError::InvalidHalfmoveClock(s) => write!(f, "invalid halfmove clock '{}'", s),
+            FenError::InvalidFullmoveNumber(s) => write!(f, "invalid fullmove number '{}'", s),
+            FenError::MissingKing { white } => write!(f, "{} has no king", color(white)),
+            FenError::TooManyKings { white } => write!(f, "{} has more than one king", color(white)),
+            FenError::PawnOnBackRank(index) => write!(f, "pawn on the back rank at index {}", index),
+            FenError::OpponentInCheck => write!(f, "the side not to move is in check"),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+pub fn letter_to_piece(symbol: char) -> Option<i8> {
     let is_white = symbol.is_ascii_uppercase();
 
     let pieces = "pbnrqk";
 
-    let piece = pieces.chars().position(|i| i == symbol.to_ascii_lowercase()).unwrap_or(0) as i8 + 1;
+    let piece = pieces.chars().position(|i| i == symbol.to_ascii_lowercase())? as i8 + 1;
 
     if is_white {
-        return piece + 8;
+        return Some(piece + 8);
+    }
+    Some(piece)
+}
+
+pub fn piece_to_letter(piece: i8) -> char {
+    let letter = "pbnrqk".chars().nth((piece & 7) as usize - 1).unwrap_or('?');
+
+    if is_white(piece) {
+        return letter.to_ascii_uppercase();
     }
-    piece
+    letter
 }
 
-pub fn translate_fen(fen: String) -> Vec<i8> {
-    let mut board: Vec<i8> = Vec::with_capacity(64);
-    for _ in 0..64 {
-        board.push(0);
+pub fn translate_placement(placement: &str) -> Result<Vec<i8>, FenError> {
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::WrongRankCount(ranks.len()));
     }
-    let mut i: usize = 0;
 
-    for c in fen.chars() {
-        if c != '/' {
-            if c.is_numeric() {
-                i += (c.to_digit(10).unwrap_or(1) - 1) as usize;
+    let mut board: Vec<i8> = vec![0; 64];
+
+    for (rank, row) in ranks.iter().enumerate() {
+        let mut file: usize = 0;
+
+        for c in row.chars() {
+            if let Some(empty) = c.to_digit(10) {
+                if empty == 0 || empty > 8 {
+                    return Err(FenError::InvalidPiece(c));
+                }
+                file += empty as usize;
             } else {
-                board[i] = letter_to_piece(c);
+                let piece = letter_to_piece(c).ok_or(FenError::InvalidPiece(c))?;
+                if file < 8 {
+                    board[index_of(file as i32, rank as i32)] = piece;
+                }
+                file += 1;
+            }
+        }
+        if file != 8 {
+            return Err(FenError::WrongSquareCount { rank, squares: file });
+        }
+    }
+
+    Ok(board)
+}
+
+fn is_piece(board: &[i8], piece: Pieces, white: bool, file: i32) -> bool {
+    board[index_of(file, back_rank(white))] == piece as i8 + if white { 8 } else { 0 }
+}
+
+// "KQkq", or for Chess960 X-FEN, where K and Q stand for the outermost rook and a file letter for
+// any other, and Shredder-FEN, where every right is the file of its rook ("HAha")
+fn translate_castling(field: &str, board: &[i8]) -> Result<CastlingRights, FenError> {
+    let mut castling_rights = CastlingRights::none();
+    if field == "-" {
+        return Ok(castling_rights);
+    }
+
+    for c in field.chars() {
+        let white = c.is_ascii_uppercase();
+        // a missing king or rook is reported once the whole position is read
+        let king_file = (0..8).find(|file| is_piece(board, Pieces::KING, white, *file)).unwrap_or(4);
+        let is_rook = |file: &i32| is_piece(board, Pieces::ROOK, white, *file);
+
+        let (king_side, rook_file) = match c.to_ascii_lowercase() {
+            'k' => (true, (king_file + 1..8).rev().find(is_rook).unwrap_or(7)),
+            'q' => (false, (0..king_file).find(is_rook).unwrap_or(0)),
+            letter @ 'a'..='h' => {
+                let file = letter as i32 - 'a' as i32;
+                (file > king_file, file)
             }
-            i += 1;
+            _ => return Err(FenError::InvalidCastling(field.to_string())),
+        };
+        if castling_rights.can_castle(white, king_side) {
+            return Err(FenError::InvalidCastling(field.to_string()));
+        }
+        castling_rights.grant_with_rook(white, king_side, rook_file);
+    }
+
+    Ok(castling_rights)
+}
+
+// castling that standard chess could not express, with the king off the e-file or a rook off the corners
+fn needs_chess960(position: &Position) -> bool {
+    [(true, true), (true, false), (false, true), (false, false)].into_iter().any(|(white, king_side)| {
+        position.castling_rights.can_castle(white, king_side)
+        && (!is_piece(&position.board, Pieces::KING, white, 4)
+            || position.castling_rights.rook_file(white, king_side) != if king_side { 7 } else { 0 })
+    })
+}
+
+fn validate(position: &Position) -> Result<(), FenError> {
+    for white in [true, false] {
+        let king = Pieces::KING as i8 + if white { 8 } else { 0 };
+        match position.board.iter().filter(|piece| **piece == king).count() {
+            0 => return Err(FenError::MissingKing { white }),
+            1 => (),
+            _ => return Err(FenError::TooManyKings { white }),
         }
-        
     }
 
-    board
-}
\ No newline at end of file
+    for (index, piece) in position.board.iter().enumerate() {
+        if piece & 7 == Pieces::PAWN as i8 && (rank_of(index) == 0 || rank_of(index) == 7) {
+            return Err(FenError::PawnOnBackRank(index));
+        }
+    }
+
+    if position.castling_rights != position.castling_rights.restricted_to(&position.board) {
+        return Err(FenError::InvalidCastling(to_castling_field(&position.castling_rights, &position.board)));
+    }
+
+    if let Some(index) = position.en_passant {
+        // the square behind a pawn that just made a double step, with both squares it crossed now empty
+        let (behind_rank, pawn) = if position.is_white_turn { (2, Pieces::PAWN as i8) } else { (5, Pieces::PAWN as i8 + 8) };
+        if rank_of(index) != behind_rank {
+            return Err(FenError::InvalidEnPassant(square_name(index)));
+        }
+        let pawn_index = if position.is_white_turn { index + 8 } else { index - 8 };
+        let start_index = if position.is_white_turn { index - 8 } else { index + 8 };
+
+        if position.board[index] != 0
+        || position.board[start_index] != 0
+        || position.board[pawn_index] != pawn {
+            return Err(FenError::InvalidEnPassant(square_name(index)));
+        }
+    }
+
+    let mut opponent = position.clone();
+    opponent.is_white_turn = !opponent.is_white_turn;
+    if opponent.in_check() {
+        return Err(FenError::OpponentInCheck);
+    }
+
+    Ok(())
+}
+
+// X-FEN, which is the usual "KQkq" in standard chess
+fn to_castling_field(castling_rights: &CastlingRights, board: &[i8]) -> String {
+    let mut field = String::new();
+    for (white, king_side) in [(true, true), (true, false), (false, true), (false, false)] {
+        if !castling_rights.can_castle(white, king_side) {
+            continue;
+        }
+        let rook_file = castling_rights.rook_file(white, king_side);
+        let mut outer_files = if king_side { rook_file + 1..8 } else { 0..rook_file };
+
+        let letter = if outer_files.any(|file| is_piece(board, Pieces::ROOK, white, file)) {
+            (b'a' + rook_file as u8) as char
+        } else if king_side {
+            'k'
+        } else {
+            'q'
+        };
+        field.push(if white { letter.to_ascii_uppercase() } else { letter });
+    }
+    if field.is_empty() {
+        field.push('-');
+    }
+    field
+}
+
+// parses all six fields, the two clocks may be left out and default to 0 and 1
+pub fn translate_fen(fen: &str) -> Result<Position, FenError> {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    if fields.len() < 4 || fields.len() > 6 {
+        return Err(FenError::WrongFieldCount(fields.len()));
+    }
+
+    let mut position = Position::new(translate_placement(fields[0])?);
+
+    position.is_white_turn = match fields[1] {
+        "w" => true,
+        "b" => false,
+        side => return Err(FenError::InvalidSideToMove(side.to_string())),
+    };
+
+    position.castling_rights = translate_castling(fields[2], &position.board)?;
+    position.chess960 = needs_chess960(&position);
+
+    position.en_passant = match fields[3] {
+        "-" => None,
+        square => Some(square_from_name(square).ok_or(FenError::InvalidEnPassant(square.to_string()))?),
+    };
+
+    if let Some(halfmove) = fields.get(4) {
+        position.halfmove_clock = halfmove.parse().map_err(|_| FenError::InvalidHalfmoveClock(halfmove.to_string()))?;
+    }
+    if let Some(fullmove) = fields.get(5) {
+        position.fullmove_number = match fullmove.parse() {
+            Ok(n) if n > 0 => n,
+            _ => return Err(FenError::InvalidFullmoveNumber(fullmove.to_string())),
+        };
+    }
+
+    validate(&position)?;
+    position.key = position.compute_key();
+
+    Ok(position)
+}
+
+
+pub fn to_fen(position: &Position) -> String {
+    let mut placement = String::new();
+
+    for rank in 0..8 {
+        let mut empty = 0;
+        for file in 0..8 {
+            let piece = position.board[index_of(file, rank)];
+            if piece == 0 {
+                empty += 1;
+                continue;
+            }
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+                empty = 0;
+            }
+            placement.push(piece_to_letter(piece));
+        }
+        if empty > 0 {
+            placement.push_str(&empty.to_string());
+        }
+        if rank < 7 {
+            placement.push('/');
+        }
+    }
+
+    let en_passant = match position.en_passant {
+        Some(index) => square_name(index),
+        None => "-".to_string(),
+    };
+
+    format!(
+        "{} {} {} {} {} {}",
+        placement,
+        if position.is_white_turn { "w" } else { "b" },
+        to_castling_field(&position.castling_rights, &position.board),
+        en_passant,
+        position.halfmove_clock,
+        position.fullmove_number
+    )
+}
@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use crate::chess::{Move, Position};
+use crate::san::to_san;
+
+// a move as it was played, with its notation and the clock of the side that made it
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameMove {
+    pub mv: Move,
+    pub san: String,
+    pub clock: Option<Duration>,
+}
+
+// the moves played from a starting position, enough to write the game out or replay it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Game {
+    pub start: Position,
+    pub moves: Vec<GameMove>,
+    // moves taken back, the last one taken back at the end, until a new move is recorded
+    pub undone: Vec<GameMove>,
+}
+
+impl Game {
+    pub fn new(start: Position) -> Game {
+        Game { start, moves: Vec::new(), undone: Vec::new() }
+    }
+
+    // whether white is to move after the first plies moves
+    fn white_to_move_after(&self, plies: usize) -> bool {
+        self.start.is_white_turn == plies.is_multiple_of(2)
+    }
+
+    // before is the position the move was played from, its notation depends on it
+    pub fn record(&mut self, before: &Position, mv: Move, clock: Option<Duration>) {
+        let san = to_san(before, &mv);
+
+        self.moves.push(GameMove { mv, san, clock });
+        self.undone.clear();
+    }
+
+    pub fn undo(&mut self) -> bool {
+        match self.moves.pop() {
+            Some(game_move) => {
+                self.undone.push(game_move);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn redo(&mut self) -> bool {
+        match self.undone.pop() {
+            Some(game_move) => {
+                self.moves.push(game_move);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // takes back at least one move and stops when the side is to move, so a player facing
+    // the bot gets back their own move along with the reply; false if the side never had a move to take back
+    pub fn undo_to_turn(&mut self, white: bool) -> bool {
+        let Some(plies) = (0..self.moves.len()).rev().find(|plies| self.white_to_move_after(*plies) == white) else {
+            return false;
+        };
+
+        while self.moves.len() > plies {
+            self.undo();
+        }
+        true
+    }
+
+    // replays at least one move and goes on until the side is to move again or nothing is left
+    pub fn redo_to_turn(&mut self, white: bool) -> bool {
+        if !self.redo() {
+            return false;
+        }
+        while self.white_to_move_after(self.moves.len()) != white && self.redo() {}
+        true
+    }
+
+    // takes back or replays moves until the first plies moves of the line are on the board;
+    // false when there was nothing to do or the line is shorter
+    pub fn go_to(&mut self, plies: usize) -> bool {
+        if plies == self.moves.len() || plies > self.moves.len() + self.undone.len() {
+            return false;
+        }
+
+        while self.moves.len() > plies {
+            self.undo();
+        }
+        while self.moves.len() < plies {
+            self.redo();
+        }
+        true
+    }
+
+    // goes to the first plies moves of the line, or one move earlier when the other side is to move
+    // there, so a player facing the bot can look through the line without the bot playing over it;
+    // one move later when there is no earlier one
+    pub fn go_to_turn(&mut self, plies: usize, white: bool) -> bool {
+        let plies = if self.white_to_move_after(plies) == white {
+            plies
+        } else if plies > 0 {
+            plies - 1
+        } else {
+            (self.moves.len() + self.undone.len()).min(1)
+        };
+        self.go_to(plies)
+    }
+
+    // the moves played followed by the ones taken back, in the order they were first played
+    pub fn line(&self) -> impl Iterator<Item = &GameMove> {
+        self.moves.iter().chain(self.undone.iter().rev())
+    }
+
+    // the position after every recorded move
+    pub fn position(&self) -> Position {
+        let mut position = self.start.clone();
+
+        for game_move in &self.moves {
+            position.play(game_move.mv);
+        }
+        position
+    }
+}
@@ -0,0 +1,16 @@
+pub mod chess;
+pub mod bitboard;
+pub mod zobrist;
+pub mod chess960;
+pub mod clock;
+pub mod fen;
+pub mod bot;
+pub mod perft;
+pub mod san;
+pub mod game;
+pub mod pgn;
+pub mod replay;
+pub mod uci;
+pub mod player;
+pub mod xboard;
+pub mod cli;
@@ -1,12 +1,20 @@
-use std::thread;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::process::exit;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
 use sdl2::event::Event;
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
-use sdl2::video::Window;
+use sdl2::render::{Canvas, Texture, TextureCreator};
+use sdl2::ttf::Font;
+use sdl2::video::{Window, WindowContext};
 use sdl2::{Sdl, VideoSubsystem};
-use sdl2::{keyboard::Keycode};
+use sdl2::keyboard::{Keycode, Mod};
 use sdl2::image::LoadTexture;
 use sdl2::image::InitFlag;
 
@@ -14,31 +22,358 @@ use std::cmp::min;
 
 use vectors::v2::V2;
 
-use crate::chess::{checkmate, file_of, index_of, is_white, make_move, rank_of, MoveResult, Pieces};
+use chess_rust::{chess, chess960, cli, fen, perft};
+use chess_rust::chess::{file_of, rank_of, Move, MoveResult, Outcome, Pieces, Position};
+use chess_rust::clock::{clock_text, flag_fall, Clock};
+use chess_rust::game::Game;
+use chess_rust::pgn::{self, Tags};
+use chess_rust::player::{Player, UciEngine};
+use chess_rust::replay::Replay;
+use chess_rust::san::{move_number, to_display_san};
 
 extern crate sdl2;
 
 mod vectors;
-mod fen;
-mod chess;
 
-mod bot;
+const BOARD_SIZE: i32 = 640;
+// the strip right of the board holding the buttons, the clocks and the move list
+const PANEL_WIDTH: u32 = 240;
 
-fn main() {
-    let debug_bitboard = true;
+fn undo_button() -> Rect {
+    Rect::new(BOARD_SIZE + 20, 20, 55, 40)
+}
+
+fn redo_button() -> Rect {
+    Rect::new(BOARD_SIZE + 85, 20, 55, 40)
+}
+
+// the move the player is looking for on its own thread, and the flag that cuts the search short
+type Search = (Arc<AtomicBool>, JoinHandle<io::Result<Option<Move>>>);
+
+// ends the game in a draw while one can be claimed, as D does
+fn claim_button() -> Rect {
+    Rect::new(BOARD_SIZE + 150, 20, 70, 40)
+}
+
+// black's clock sits at the top of the panel and white's at the bottom, on the side of their pieces
+fn clock_box(white: bool) -> Rect {
+    Rect::new(BOARD_SIZE + 10, if white { BOARD_SIZE - 70 } else { 80 }, PANEL_WIDTH - 20, 50)
+}
+
+// the segments lit for each digit, bit 0 being the top one, then clockwise with the middle one last
+const DIGIT_SEGMENTS: [u8; 10] = [0x3F, 0x06, 0x5B, 0x4F, 0x66, 0x6D, 0x7D, 0x07, 0x7F, 0x6F];
+const DIGIT_WIDTH: i32 = 14;
+const DIGIT_HEIGHT: i32 = 28;
+const SEGMENT: i32 = 3;
+
+fn text_width(text: &str) -> i32 {
+    text.chars().map(|c| if c == ':' { 8 } else { DIGIT_WIDTH + 4 }).sum::<i32>() - 4
+}
+
+// a time such as "1:29:59" in seven-segment digits, centered in the box
+fn draw_clock(canvas: &mut Canvas<Window>, area: Rect, time: Duration, running: bool) {
+    let (background, foreground) = match (running, time.is_zero()) {
+        (_, true) => (Color::RGB(190, 60, 60), Color::RGB(246, 214, 189)),
+        (true, false) => (Color::RGB(246, 214, 189), Color::RGB(78, 73, 95)),
+        (false, false) => (Color::RGB(78, 73, 95), Color::RGB(246, 214, 189)),
+    };
+    canvas.set_draw_color(background);
+    _ = canvas.fill_rect(area);
+
+    let text = clock_text(time);
+    let mut x = area.center().x() - text_width(&text) / 2;
+    let y = area.center().y() - DIGIT_HEIGHT / 2;
+    let half = DIGIT_HEIGHT / 2;
+
+    canvas.set_draw_color(foreground);
+    for c in text.chars() {
+        let Some(digit) = c.to_digit(10) else {
+            _ = canvas.fill_rect(Rect::new(x, y + 7, SEGMENT as u32, SEGMENT as u32));
+            _ = canvas.fill_rect(Rect::new(x, y + DIGIT_HEIGHT - 10, SEGMENT as u32, SEGMENT as u32));
+            x += 8;
+            continue;
+        };
+
+        let segments = [
+            (x, y, DIGIT_WIDTH, SEGMENT),
+            (x + DIGIT_WIDTH - SEGMENT, y, SEGMENT, half),
+            (x + DIGIT_WIDTH - SEGMENT, y + half, SEGMENT, half),
+            (x, y + DIGIT_HEIGHT - SEGMENT, DIGIT_WIDTH, SEGMENT),
+            (x, y + half, SEGMENT, half),
+            (x, y, SEGMENT, half),
+            (x, y + half - SEGMENT / 2, DIGIT_WIDTH, SEGMENT),
+        ];
+        for (i, (x, y, width, height)) in segments.into_iter().enumerate() {
+            if DIGIT_SEGMENTS[digit as usize] & 1 << i != 0 {
+                _ = canvas.fill_rect(Rect::new(x, y, width as u32, height as u32));
+            }
+        }
+        x += DIGIT_WIDTH + 4;
+    }
+}
+
+const ROW_HEIGHT: i32 = 22;
+// where the move number, white's move and black's move of a row start
+const COLUMNS: [i32; 3] = [4, 52, 136];
+
+// the moves fill the panel between the buttons, or the clocks when there are any, and its bottom
+fn move_list_area(clock: bool) -> Rect {
+    let (top, bottom) = if clock { (140, BOARD_SIZE - 80) } else { (70, BOARD_SIZE - 10) };
+
+    Rect::new(BOARD_SIZE + 10, top, PANEL_WIDTH - 20, (bottom - top) as u32)
+}
+
+fn visible_rows(area: Rect) -> usize {
+    area.height() as usize / ROW_HEIGHT as usize
+}
+
+// strings drawn with the bundled font, each rendered to a texture the first time it is needed
+struct TextRenderer<'ttf, 'a> {
+    font: Font<'ttf, 'static>,
+    texture_creator: &'a TextureCreator<WindowContext>,
+    textures: HashMap<String, Texture<'a>>,
+}
+
+impl TextRenderer<'_, '_> {
+    fn draw(&mut self, canvas: &mut Canvas<Window>, text: &str, x: i32, y: i32) {
+        if !self.textures.contains_key(text) {
+            let Ok(surface) = self.font.render(text).blended(Color::RGB(246, 214, 189)) else {
+                return;
+            };
+            let Ok(texture) = self.texture_creator.create_texture_from_surface(&surface) else {
+                return;
+            };
+            self.textures.insert(text.to_string(), texture);
+        }
+
+        let texture = &self.textures[text];
+        let attributes = texture.query();
+        _ = canvas.copy(texture, None, Rect::new(x, y, attributes.width, attributes.height));
+    }
+}
+
+// the moves two to a row after their number, black's first move alone on its row when black started;
+// the move that led to the position shown is highlighted
+fn draw_move_list(canvas: &mut Canvas<Window>, text: &mut TextRenderer, area: Rect, start: &Position, sans: &[&str], plies: usize, scroll: usize) {
+    let first_slot = !start.is_white_turn as usize;
+
+    for row in scroll..scroll + visible_rows(area) {
+        if 2 * row >= first_slot + sans.len() {
+            break;
+        }
+        let y = area.y() + (row - scroll) as i32 * ROW_HEIGHT;
+        text.draw(canvas, &format!("{}.", start.fullmove_number as usize + row), area.x() + COLUMNS[0], y + 2);
+
+        for column in 0..2 {
+            let Some(ply) = (2 * row + column).checked_sub(first_slot).filter(|ply| *ply < sans.len()) else {
+                continue;
+            };
+            let x = area.x() + COLUMNS[column + 1];
+            if ply + 1 == plies {
+                canvas.set_draw_color(Color::RGB(120, 112, 130));
+                _ = canvas.fill_rect(Rect::new(x - 4, y, 84, ROW_HEIGHT as u32));
+            }
+            text.draw(canvas, sans[ply], x, y + 2);
+        }
+    }
+}
+
+// a button with an arrow pointing left or right, greyed out when it would do nothing
+fn draw_arrow_button(canvas: &mut Canvas<Window>, button: Rect, left: bool, enabled: bool) {
+    canvas.set_draw_color(if enabled { Color::RGB(246, 214, 189) } else { Color::RGB(120, 112, 130) });
+    _ = canvas.fill_rect(button);
+
+    // vertical strokes growing from the tip of the arrow
+    let center = button.center();
+    canvas.set_draw_color(Color::RGB(78, 73, 95));
+    for i in 0..12 {
+        let x = if left { center.x() - 6 + i } else { center.x() + 6 - i };
+        _ = canvas.draw_line((x, center.y() - i), (x, center.y() + i));
+    }
+}
+
+// takes back the last move, or against the bot the human's last move along with the reply to it
+fn undo(game: &mut Game, bot_side: Option<bool>) -> bool {
+    match bot_side {
+        Some(bot) => game.undo_to_turn(!bot),
+        None => game.undo(),
+    }
+}
+
+fn redo(game: &mut Game, bot_side: Option<bool>) -> bool {
+    match bot_side {
+        Some(bot) => game.redo_to_turn(!bot),
+        None => game.redo(),
+    }
+}
+
+// against the bot a jump lands on the human's move, or the bot would play over the rest of the line
+fn jump(game: &mut Game, plies: usize, bot_side: Option<bool>) -> bool {
+    match bot_side {
+        Some(bot) => game.go_to_turn(plies, !bot),
+        None => game.go_to(plies),
+    }
+}
+
+fn run_perft(depth: u32, fen: &str) {
+    let position = match fen::translate_fen(fen) {
+        Ok(position) => position,
+        Err(error) => {
+            eprintln!("--fen: {}", error);
+            exit(2);
+        }
+    };
+
+    let start = Instant::now();
+    let counts = perft::divide(&position, depth);
+
+    for (name, nodes) in &counts {
+        println!("{}: {}", name, nodes);
+    }
+    println!("\nNodes searched: {}", counts.iter().map(|(_, nodes)| nodes).sum::<u64>());
+    println!("Time: {:.3}s", start.elapsed().as_secs_f32());
+}
+
+// the games of the file that could be read, the others are reported and skipped
+fn load_pgn(path: &str) -> Replay {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) => {
+            eprintln!("--pgn {}: {}", path, error);
+            exit(2);
+        }
+    };
+
+    let mut games = Vec::new();
+    for (i, game) in pgn::read_pgn(&text).into_iter().enumerate() {
+        match game {
+            Ok(game) => games.push(game),
+            Err(error) => eprintln!("--pgn {}: game {}: {}", path, i + 1, error),
+        }
+    }
+    if games.is_empty() {
+        eprintln!("--pgn {}: no game to replay", path);
+        exit(2);
+    }
+    Replay::new(games)
+}
+
+fn print_replay(replay: &Replay) {
+    let game = replay.current();
+    let players = format!("{} - {}", game.tag("White").unwrap_or("?"), game.tag("Black").unwrap_or("?"));
+    let step = match replay.last_move() {
+        Some(node) => {
+            let before = game.position_at(&replay.path[..replay.path.len() - 1]).unwrap();
+            format!("{} {} {}", move_number(&before), to_display_san(&before, &node.mv), node.comments.join(" "))
+        }
+        None => format!("start {}", game.comments.join(" ")),
+    };
+
+    println!("game {}/{} ({}): {}", replay.game + 1, replay.games.len(), players, step.trim_end());
+}
+
+// plays the move if it is legal, adding it to the game and printing it the way it is written, e.g. "12... exd3 e.p.";
+// with a clock the move only counts if it came before the flag fell, and the time left is kept with it
+fn play_move(position: &mut Position, game: &mut Game, clock: Option<&mut Clock>, mv: Move, player: &str) -> Vec<MoveResult> {
+    let now = Instant::now();
+    if let Some(white) = clock.as_ref().and_then(|clock| clock.flag_fallen(now)) {
+        return vec![MoveResult::GameOver(flag_fall(position, white))];
+    }
+
+    let before = position.clone();
+    let results = position.make_move(mv);
+
+    if !results.contains(&MoveResult::Fail) {
+        let time_left = clock.map(|clock| {
+            clock.punch(now);
+            clock.remaining[before.is_white_turn as usize]
+        });
+        game.record(&before, mv, time_left);
+        println!("{}{} {}", player, move_number(&before), to_display_san(&before, &mv));
+        if let Some(draw) = position.draw_claim() {
+            println!("{} may be claimed with D or the Draw button", draw);
+        }
+    }
+    results
+}
 
-    let bot_playing = false;
+fn save_pgn(path: &str, game: &Game, tags: &Tags, outcome: Option<Outcome>) {
+    match fs::write(path, pgn::write_pgn(game, tags, outcome)) {
+        Ok(()) => println!("game saved to {}", path),
+        Err(error) => eprintln!("{}: {}", path, error),
+    }
+}
 
-    let mut board: Vec<i8> = fen::translate_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR".to_string());
+fn main() {
+    let options = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(cli::Command::Play(options)) => options,
+        Ok(cli::Command::Perft { depth, fen }) => {
+            run_perft(depth, &fen);
+            return;
+        }
+        Err(message) => {
+            eprintln!("{}\n{}", message, cli::USAGE);
+            exit(2);
+        }
+    };
+
+    let mut position = match fen::translate_fen(&options.fen) {
+        Ok(position) => position,
+        Err(error) => {
+            eprintln!("--fen: {}", error);
+            exit(2);
+        }
+    };
+    // castling is played by dropping the king on the rook it castles with
+    if let Some(n) = options.chess960 {
+        position = chess960::start_position(n).unwrap();
+        println!("Chess960 position {}: {}", n, fen::to_fen(&position));
+    }
 
-    let mut en_passant_index: usize = 999;
-    let mut will_end = false;
+    // a loaded game is only looked at, the pieces cannot be moved
+    let mut replay = options.pgn.as_deref().map(load_pgn);
+    if let Some(replay) = &replay {
+        position = replay.position();
+        print_replay(replay);
+    }
 
-    let res: (u32, u32) = (640, 640);
+    let player = match &options.engine {
+        Some(path) => match UciEngine::start(path, options.bot_limits) {
+            Ok(engine) => Player::Uci(engine),
+            Err(error) => {
+                eprintln!("--engine {}: {}", path, error);
+                exit(2);
+            }
+        },
+        None => Player::Bot(options.bot_limits),
+    };
+    // the player thinks on a thread of its own so the window and the clocks keep going meanwhile
+    let player_name = player.name().to_string();
+    let player = Arc::new(Mutex::new(player));
+    // the side played by the bot or the engine, handed back to the human if the engine fails
+    let mut bot_side = if replay.is_some() { None } else { options.bot };
+
+    // draws that could be claimed only end the game once a player claims them
+    let mut outcome: Option<Outcome> = None;
+    let mut claim_draw = false;
+
+    let mut game = Game::new(position.clone());
+    let tags = Tags {
+        white: if bot_side == Some(true) { &player_name } else { "Human" }.to_string(),
+        black: if bot_side == Some(false) { &player_name } else { "Human" }.to_string(),
+        ..Tags::default()
+    };
+    let pgn_path = options.save_pgn.clone().unwrap_or("game.pgn".to_string());
+
+    // the side to move starts thinking once the window is up, a replay has no clock
+    let mut clock = options.clock.clone().filter(|_| replay.is_none()).map(Clock::new);
+
+    let res: (u32, u32) = (BOARD_SIZE as u32 + PANEL_WIDTH, BOARD_SIZE as u32);
 
     let sdl_context: Sdl = sdl2::init().unwrap();
     let video_subsystem: VideoSubsystem = sdl_context.video().unwrap();
     let _image_context = sdl2::image::init(InitFlag::PNG | InitFlag::JPG).unwrap();
+    let ttf_context = sdl2::ttf::init().unwrap();
     let window: Window = video_subsystem
         .window("Chess", res.0, res.1)
         .build()
@@ -46,6 +381,11 @@ fn main() {
 
     let mut canvas = window.into_canvas().build().unwrap();
     let texture_creator = canvas.texture_creator();
+    let mut text = TextRenderer {
+        font: ttf_context.load_font("src/fonts/DejaVuSansMono.ttf", 14).unwrap(),
+        texture_creator: &texture_creator,
+        textures: HashMap::new(),
+    };
     
     let b_pieces = vec![
         texture_creator.load_texture("src/images/b_pawn.png").ok().unwrap(),
@@ -71,13 +411,25 @@ fn main() {
 
     let mut event_pump = sdl_context.event_pump().unwrap();
 
+    if let Some(clock) = &mut clock {
+        clock.start(position.is_white_turn, Instant::now());
+    }
+
     let mut mouse_coords = V2::zero();
     let mut pick_up: bool = false;
     let mut release: bool = false;
     let mut hand: i8 = 0;
     let mut original_index: usize = 0;
+    // Some(false) to take a move back, Some(true) to play it again
+    let mut history_step: Option<bool> = None;
+    // the number of moves to show when one was clicked in the move list
+    let mut ply_clicked: Option<usize> = None;
+    let mut search: Option<Search> = None;
+    // the first row of the move list in view, and how many moves were shown when it was last kept in view
+    let mut list_scroll: usize = 0;
+    let mut list_plies: Option<usize> = None;
+    let list_area = move_list_area(clock.is_some());
 
-    let mut is_white_turn = true;
     let mut pawn_promoting = false;
     let mut promotion_index = 0;
     let promotion_choices = [Pieces::BISH as i8, Pieces::KNIG as i8, Pieces::ROOK as i8, Pieces::QUEE as i8];
@@ -95,6 +447,17 @@ fn main() {
     'main: loop {
         let dt = Instant::now();
 
+        if outcome.is_none() && let Some(white) = clock.as_ref().and_then(|clock| clock.flag_fallen(dt)) {
+            outcome = Some(flag_fall(&position, white));
+        }
+        // the frame that ended the game has been shown
+        if outcome.is_some() {
+            if options.save_pgn.is_some() {
+                save_pgn(&pgn_path, &game, &tags, outcome);
+            }
+            break 'main;
+        }
+
         for event in event_pump.poll_iter() {
             match event {
                 Event::Quit { .. }
@@ -102,15 +465,88 @@ fn main() {
                     keycode: Some(Keycode::Escape),
                     ..
                 } => break 'main,
+                Event::KeyDown {
+                    keycode: Some(Keycode::C),
+                    keymod,
+                    ..
+                } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    let fen = fen::to_fen(&position);
+
+                    println!("{}", fen);
+                    _ = video_subsystem.clipboard().set_clipboard_text(&fen);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::S),
+                    keymod,
+                    ..
+                } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => save_pgn(&pgn_path, &game, &tags, outcome),
+                Event::KeyDown {
+                    keycode: Some(keycode @ (Keycode::Z | Keycode::Y)),
+                    keymod,
+                    ..
+                } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) && replay.is_none() => history_step = Some(keycode == Keycode::Y),
+                Event::KeyDown {
+                    keycode: Some(Keycode::D),
+                    ..
+                } if replay.is_none() => claim_draw = true,
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    ..
+                } if replay.is_some() => {
+                    let replay = replay.as_mut().unwrap();
+                    let stepped = match keycode {
+                        Keycode::Right => replay.forward(),
+                        Keycode::Left => replay.back(),
+                        Keycode::Up => replay.switch_variation(false),
+                        Keycode::Down => replay.switch_variation(true),
+                        Keycode::Home => {
+                            replay.to_start();
+                            true
+                        }
+                        Keycode::End => {
+                            replay.to_end();
+                            true
+                        }
+                        Keycode::PageUp => replay.switch_game(false),
+                        Keycode::PageDown => replay.switch_game(true),
+                        _ => false,
+                    };
+                    if stepped {
+                        position = replay.position();
+                        print_replay(replay);
+                    }
+                }
                 Event::MouseMotion { x, y, .. } => {
                     mouse_coords.x = x as f32;
                     mouse_coords.y = y as f32;
                 }
+                Event::MouseWheel { y, .. } if list_area.contains_point((mouse_coords.x as i32, mouse_coords.y as i32)) => {
+                    list_scroll = list_scroll.saturating_add_signed(-y as isize);
+                }
                 Event::MouseButtonDown { .. } => {
-                    pick_up = !pawn_promoting; // makes sure you can't pickup a piece when you're promoting
+                    pick_up = !pawn_promoting && replay.is_none() && (mouse_coords.x as i32) < BOARD_SIZE; // makes sure you can't pickup a piece when you're promoting or replaying
                 }
                 Event::MouseButtonUp { .. } => {
-                    if pawn_promoting {
+                    let mouse = (mouse_coords.x as i32, mouse_coords.y as i32);
+
+                    if replay.is_none() && undo_button().contains_point(mouse) {
+                        history_step = Some(false);
+                    } else if replay.is_none() && redo_button().contains_point(mouse) {
+                        history_step = Some(true);
+                    } else if replay.is_none() && claim_button().contains_point(mouse) {
+                        claim_draw = true;
+                        hand = 0;
+                        legal_piece_moves.clear();
+                    } else if list_area.contains_point(mouse) {
+                        // a piece dropped on the panel goes back to its square
+                        hand = 0;
+                        legal_piece_moves.clear();
+                        // a click on the move number goes to white's move
+                        let start = replay.as_ref().map_or(&game.start, |replay| &replay.current().start);
+                        let row = list_scroll + ((mouse.1 - list_area.y()) / ROW_HEIGHT) as usize;
+                        let column = (mouse.0 - list_area.x() >= COLUMNS[2]) as usize;
+                        ply_clicked = (2 * row + column).checked_sub(!start.is_white_turn as usize).map(|ply| ply + 1);
+                    } else if pawn_promoting {
                         if mouse_coords.x as i32 >= promotion_box_x
                         && mouse_coords.x as i32 <= promotion_box_x + 320
                         && mouse_coords.y as i32 >= promotion_box_y + 35
@@ -118,7 +554,13 @@ fn main() {
                             let choice_index = (mouse_coords.x as i32 - promotion_box_x) / 80;
                             promoted_into = promotion_choices[choice_index as usize];
 
-                            board[promotion_index] = promoted_into + if !is_white_turn { 8 } else { 0 };
+                            // the pawn only moves once its new piece is known
+                            let mv = position.new_move(original_index, promotion_index, Pieces::from_piece(promoted_into));
+                            for result in play_move(&mut position, &mut game, clock.as_mut(), mv, "") {
+                                if let MoveResult::GameOver(x) = result {
+                                    outcome = Some(x);
+                                }
+                            }
                             pawn_promoting = false;
                         }
                     } else {
@@ -128,16 +570,62 @@ fn main() {
                 _ => (),
             }
         }
-        if bot_playing && !is_white_turn && !pawn_promoting {
-            let (index, move_index) = bot::turn(is_white_turn, &board, bitboard, en_passant_index);
-
-            board[move_index] = board[index];
-            board[index] = 0;
-            is_white_turn = true;
-
-            if checkmate(is_white_turn, &board, bitboard, en_passant_index) {
-                println!("Checkmate !");
-                will_end = true;
+        if let Some(replay) = &mut replay
+            && let Some(plies) = ply_clicked.take()
+            && plies <= replay.line().len()
+            && replay.go_to(plies)
+        {
+            position = replay.position();
+            print_replay(replay);
+        }
+        let step = match (history_step.take(), ply_clicked.take()) {
+            (Some(true), _) => redo(&mut game, bot_side).then_some("redo"),
+            (Some(false), _) => undo(&mut game, bot_side).then_some("undo"),
+            (None, Some(plies)) => jump(&mut game, plies, bot_side).then_some("jump"),
+            (None, None) => None,
+        };
+        if let Some(step) = step {
+            // the move being looked for is for a position no longer on the board
+            if let Some((stop, _)) = search.take() {
+                stop.store(true, Ordering::Relaxed);
+            }
+            position = game.position();
+            outcome = position.outcome();
+            // the time spent on the moves taken back is given back as well
+            if let Some(clock) = &mut clock {
+                clock.rewind(&game);
+                clock.start(position.is_white_turn, Instant::now());
+            }
+            hand = 0;
+            legal_piece_moves.clear();
+            pawn_promoting = false;
+            release = false;
+            println!("{} to {}", step, move_number(&position));
+        }
+        if std::mem::take(&mut claim_draw) && outcome.is_none() && let Some(draw) = position.draw_claim() {
+            outcome = Some(draw);
+        }
+        if bot_side == Some(position.is_white_turn) && !pawn_promoting && search.is_none() {
+            let stop = Arc::new(AtomicBool::new(false));
+            let (player, game, clock, searching) = (Arc::clone(&player), game.clone(), clock.clone(), Arc::clone(&stop));
+            search = Some((stop, thread::spawn(move || player.lock().unwrap().choose_move(&game, clock.as_ref(), &searching))));
+        }
+        if search.as_ref().is_some_and(|(_, handle)| handle.is_finished()) {
+            let (_, handle) = search.take().unwrap();
+            match handle.join().unwrap() {
+                Ok(Some(mv)) => {
+                    let name = format!("{}: ", player_name);
+                    for result in play_move(&mut position, &mut game, clock.as_mut(), mv, &name) {
+                        if let MoveResult::GameOver(x) = result {
+                            outcome = Some(x);
+                        }
+                    }
+                }
+                Ok(None) => (),
+                Err(error) => {
+                    eprintln!("{}", error);
+                    bot_side = None;
+                }
             }
         }
 
@@ -147,12 +635,12 @@ fn main() {
 
             let index = (y * 8 + x) as usize;
             original_index = index;
-            hand = board[index];
-            board[index] = 0;
+            // the player's pieces stay put while it thinks
+            hand = if bot_side == Some(position.is_white_turn) { 0 } else { position.board[index] };
 
             if hand != 0 {
-                bitboard = chess::generate_bit_board(&board, is_white_turn);
-                legal_piece_moves = chess::generate_legal_moves(hand, original_index as i32, &board, bitboard, en_passant_index);
+                bitboard = position.attacked_squares();
+                legal_piece_moves = position.legal_moves(original_index);
             }
 
             pick_up = false;
@@ -161,36 +649,35 @@ fn main() {
             let x = mouse_coords.x as i32 / 80;
             let y = mouse_coords.y as i32 / 80;
 
+            // a piece dropped off the board goes back to its square
             if chess::in_bounds(x, y) {
                 let index = chess::index_of(x, y) as usize;
 
-                let res = make_move(is_white_turn, &mut board, &mut legal_piece_moves, bitboard, hand, original_index, index, &mut en_passant_index, false);
-
-                println!("{:?}", res);
-
-                let mut fail = false;
-
-                match res {
-                    MoveResult::Promotion(x) => promotion_index = x,
-                    MoveResult::Checkmate => will_end = true,
-                    MoveResult::Fail => fail = true,
-                    _ => (),
-                }
-                hand = 0;
-                if !fail {
-                    is_white_turn = !is_white_turn;
-                    en_passant_index = 999;
+                // a pawn reaching the last rank waits for the promotion dialog
+                if position.find_move(original_index, index, Some(Pieces::QUEE)).is_some() {
+                    promotion_index = index;
+                    pawn_promoting = true;
+                } else {
+                    let mv = position.new_move(original_index, index, None);
+                    for result in play_move(&mut position, &mut game, clock.as_mut(), mv, "") {
+                        if let MoveResult::GameOver(x) = result {
+                            outcome = Some(x);
+                        }
+                    }
                 }
-
-                legal_piece_moves.clear();
-
-                release = false;
             }
+            hand = 0;
+
+            legal_piece_moves.clear();
         }
+        // a click on an empty square holds nothing to drop
+        release = false;
 
         canvas.set_draw_color(Color::RGB(0, 0, 0));
         canvas.clear();
 
+        let checked_king = if position.in_check() { position.king_index(position.is_white_turn) } else { None };
+
         for i in 0..64 {
             let r = Rect::new(
                 i % 8 * 80,
@@ -202,9 +689,12 @@ fn main() {
             let mut color = if i % 2 + (i / 8) % 2 == 1 { Color::RGB(78, 73, 95) } 
                 else { Color::RGB(246, 214, 189) };
             
-            if 2u64.pow(i as u32) & bitboard != 0 && debug_bitboard {
+            if 2u64.pow(i as u32) & bitboard != 0 && options.debug_attacks {
                 color = Color::RGB(255, color.g / 4, color.b / 4);
             }
+            if checked_king == Some(i as usize) {
+                color = Color::RGB(color.r / 2 + 110, color.g / 3, color.b / 3);
+            }
 
             canvas.set_draw_color(color);
 
@@ -212,14 +702,14 @@ fn main() {
         }
 
         for i in 0..64 {
-            if board[i] != 0 {
+            if position.board[i] != 0 && !(hand != 0 && i == original_index) {
                 let texture;
 
-                if chess::is_white(board[i]){
-                    let index = board[i] as usize - 9;
+                if chess::is_white(position.board[i]){
+                    let index = position.board[i] as usize - 9;
                     texture = &w_pieces[index];
                 } else {
-                    let index = board[i] as usize - 1;
+                    let index = position.board[i] as usize - 1;
                     texture = &b_pieces[index];
                 }
 
@@ -244,6 +734,39 @@ fn main() {
             }
         }
 
+        canvas.set_draw_color(Color::RGB(40, 37, 50));
+        _ = canvas.fill_rect(Rect::new(BOARD_SIZE, 0, PANEL_WIDTH, BOARD_SIZE as u32));
+        if replay.is_none() {
+            draw_arrow_button(&mut canvas, undo_button(), true, !game.moves.is_empty());
+            draw_arrow_button(&mut canvas, redo_button(), false, !game.undone.is_empty());
+
+            let claimable = position.draw_claim().is_some();
+            canvas.set_draw_color(if claimable { Color::RGB(153, 117, 119) } else { Color::RGB(78, 73, 95) });
+            _ = canvas.fill_rect(claim_button());
+            text.draw(&mut canvas, "Draw", claim_button().x() + 17, claim_button().y() + 11);
+        }
+        if let Some(clock) = &clock {
+            let now = Instant::now();
+            for white in [true, false] {
+                let running = clock.running.is_some_and(|(side, _)| side == white);
+                draw_clock(&mut canvas, clock_box(white), clock.time_left(white, now), running);
+            }
+        }
+
+        let (start, sans, plies) = match &replay {
+            Some(replay) => (&replay.current().start, replay.line().iter().map(|node| node.san.as_str()).collect::<Vec<_>>(), replay.path.len()),
+            None => (&game.start, game.line().map(|game_move| game_move.san.as_str()).collect(), game.moves.len()),
+        };
+        let rows = (!start.is_white_turn as usize + sans.len()).div_ceil(2);
+        // a new position brings its move into view, the wheel can scroll it away again
+        if list_plies != Some(plies) && plies > 0 {
+            let row = (!start.is_white_turn as usize + plies - 1) / 2;
+            list_scroll = list_scroll.clamp((row + 1).saturating_sub(visible_rows(list_area)), row);
+        }
+        list_plies = Some(plies);
+        list_scroll = list_scroll.min(rows.saturating_sub(visible_rows(list_area)));
+        draw_move_list(&mut canvas, &mut text, list_area, start, &sans, plies, list_scroll);
+
         if hand != 0 {
             let texture;
 
@@ -272,7 +795,7 @@ fn main() {
             _ = canvas.copy(&promotion_texture, src_rect, dest_rect);
 
             for i in 0..4 {
-                let texture = if !is_white_turn { &w_pieces[promotion_choices[i] as usize - 1] } else { &b_pieces[promotion_choices[i] as usize - 1] };
+                let texture = if position.is_white_turn { &w_pieces[promotion_choices[i] as usize - 1] } else { &b_pieces[promotion_choices[i] as usize - 1] };
 
                 let attributes = texture.query();
                 let src = Rect::new(0, 0, attributes.width, attributes.height);
@@ -284,10 +807,6 @@ fn main() {
 
         canvas.present();
 
-        if will_end {
-            break 'main;
-        }
-
         let frame_delay = 1.0 / max_fps;
         delta_time = dt.elapsed().as_secs_f32();
 
@@ -296,10 +815,21 @@ fn main() {
         }
         
     }
-    let checkmate_texture = texture_creator.load_texture("src/images/checkmate.png").ok().unwrap();
-    if !will_end {
-        return;
+    // an engine told to stop still answers before it is sent quit
+    if let Some((stop, handle)) = search.take() {
+        stop.store(true, Ordering::Relaxed);
+        _ = handle.join();
     }
+    let Some(outcome) = outcome else {
+        return;
+    };
+    println!("{}", outcome);
+    _ = canvas.window_mut().set_title(&format!("Chess - {}", outcome));
+
+    let gameover_texture = match outcome.winner() {
+        Some(_) => texture_creator.load_texture("src/images/checkmate.png").ok().unwrap(),
+        None => texture_creator.load_texture("src/images/draw.png").ok().unwrap(),
+    };
     'gameover: loop {
         let dt = Instant::now();
 
@@ -314,19 +844,31 @@ fn main() {
             }
         }
 
-        let checkmate_attributes = checkmate_texture.query();
-        let src = Rect::new(0, 0, checkmate_attributes.width, checkmate_attributes.height);
+        let gameover_attributes = gameover_texture.query();
+        let src = Rect::new(0, 0, gameover_attributes.width, gameover_attributes.height);
         let dest = Rect::new(145, 245, 350, 130);
 
-        _ = canvas.copy(&checkmate_texture, src, dest);
+        _ = canvas.copy(&gameover_texture, src, dest);
 
-        let texture = if !is_white_turn { &w_pieces[Pieces::KING as usize - 1] } else { &b_pieces[Pieces::KING as usize - 1] };
+        let white_king = &w_pieces[Pieces::KING as usize - 1];
+        let black_king = &b_pieces[Pieces::KING as usize - 1];
 
-        let attributes = texture.query();
-        let src = Rect::new(0, 0, attributes.width, attributes.height);
-        let dst = Rect::new(280, 285, 80, 80);
+        // the winner's king takes the whole frame, a draw shares it between both kings
+        let kings = match outcome.winner() {
+            Some(true) => vec![(white_king, Rect::new(280, 285, 80, 80))],
+            Some(false) => vec![(black_king, Rect::new(280, 285, 80, 80))],
+            None => vec![
+                (white_king, Rect::new(280, 305, 40, 40)),
+                (black_king, Rect::new(320, 305, 40, 40)),
+            ],
+        };
+
+        for (texture, dst) in kings {
+            let attributes = texture.query();
+            let src = Rect::new(0, 0, attributes.width, attributes.height);
 
-        _ = canvas.copy(texture, src, dst);
+            _ = canvas.copy(texture, src, dst);
+        }
         
 
         canvas.present();
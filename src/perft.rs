@@ -0,0 +1,41 @@
+use crate::chess::Position;
+
+// counts on one position, every move being played and taken back in place
+fn count(position: &mut Position, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = position.legal_move_list();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for mv in moves {
+        let undo = position.play(mv);
+        nodes += count(position, depth - 1);
+        position.unmake_move(&undo);
+    }
+    nodes
+}
+
+// number of leaf nodes of the legal move tree at the given depth, promotions counted once per piece
+pub fn perft(position: &Position, depth: u32) -> u64 {
+    count(&mut position.clone(), depth)
+}
+
+// perft split by root move, sorted by move name
+pub fn divide(position: &Position, depth: u32) -> Vec<(String, u64)> {
+    let mut position = position.clone();
+    let mut counts: Vec<(String, u64)> = Vec::new();
+
+    for mv in position.legal_move_list() {
+        let undo = position.play(mv);
+        counts.push((mv.name(), count(&mut position, depth.saturating_sub(1))));
+        position.unmake_move(&undo);
+    }
+
+    counts.sort();
+    counts
+}
@@ -0,0 +1,531 @@
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::chess::{Move, Outcome, Position};
+use crate::fen::{to_fen, translate_fen, FenError, STARTING_FEN};
+use crate::game::Game;
+use crate::san::{parse_san, to_san};
+
+// export lines are kept under 80 characters
+const LINE_LENGTH: usize = 79;
+
+// the Seven Tag Roster, the result coming from the game itself
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tags {
+    pub event: String,
+    pub site: String,
+    pub date: String,
+    pub round: String,
+    pub white: String,
+    pub black: String,
+}
+
+impl Default for Tags {
+    fn default() -> Tags {
+        Tags {
+            event: "Casual game".to_string(),
+            site: "ChessRust".to_string(),
+            date: today(),
+            round: "-".to_string(),
+            white: "?".to_string(),
+            black: "?".to_string(),
+        }
+    }
+}
+
+// "YYYY.MM.DD" in UTC
+pub fn today() -> String {
+    let days = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / 86_400;
+
+    date_from_days(days as i64)
+}
+
+// civil date of a day count since 1970-01-01
+fn date_from_days(days: i64) -> String {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+// "*" while the game is still going on
+pub fn result_token(outcome: Option<Outcome>) -> &'static str {
+    match outcome.map(|outcome| outcome.winner()) {
+        Some(Some(true)) => "1-0",
+        Some(Some(false)) => "0-1",
+        Some(None) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+// "{[%clk 1:05:09]}" comment with the time left after a move
+pub fn clock_comment(clock: Duration) -> String {
+    let seconds = clock.as_secs();
+
+    format!("{{[%clk {}:{:02}:{:02}]}}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// tag pairs, a blank line, then the movetext wrapped at 80 columns and ending on the result
+pub fn write_pgn(game: &Game, tags: &Tags, outcome: Option<Outcome>) -> String {
+    let result = result_token(outcome);
+    let mut pgn = String::new();
+
+    for (name, value) in [
+        ("Event", &tags.event),
+        ("Site", &tags.site),
+        ("Date", &tags.date),
+        ("Round", &tags.round),
+        ("White", &tags.white),
+        ("Black", &tags.black),
+    ] {
+        pgn += &format!("[{} \"{}\"]\n", name, escape(value));
+    }
+    pgn += &format!("[Result \"{}\"]\n", result);
+
+    if game.start.chess960 {
+        pgn += "[Variant \"Chess960\"]\n";
+    }
+    let start_fen = to_fen(&game.start);
+    if start_fen != STARTING_FEN {
+        pgn += "[SetUp \"1\"]\n";
+        pgn += &format!("[FEN \"{}\"]\n", start_fen);
+    }
+    pgn += "\n";
+
+    let mut tokens: Vec<String> = Vec::new();
+    let mut position = game.start.clone();
+    // black's move number is repeated at the start and after a comment
+    let mut after_comment = true;
+    for game_move in &game.moves {
+        if position.is_white_turn {
+            tokens.push(format!("{}.", position.fullmove_number));
+        } else if after_comment {
+            tokens.push(format!("{}...", position.fullmove_number));
+        }
+        tokens.push(game_move.san.clone());
+        after_comment = false;
+        if let Some(clock) = game_move.clock {
+            tokens.push(clock_comment(clock));
+            after_comment = true;
+        }
+
+        position.play(game_move.mv);
+    }
+    tokens.push(result.to_string());
+
+    let mut line = String::new();
+    for token in tokens {
+        if !line.is_empty() && line.len() + 1 + token.len() > LINE_LENGTH {
+            pgn += &line;
+            pgn += "\n";
+            line.clear();
+        }
+        if !line.is_empty() {
+            line += " ";
+        }
+        line += &token;
+    }
+    pgn += &line;
+    pgn += "\n";
+
+    pgn
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PgnError {
+    InvalidTag(String),
+    InvalidFen(FenError),
+    IllegalMove { san: String, fullmove_number: u32, white: bool },
+    UnterminatedComment,
+    UnterminatedVariation,
+    UnexpectedClose,
+    VariationWithoutMove,
+    UnexpectedCharacter(char),
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgnError::InvalidTag(tag) => write!(f, "invalid tag pair '{}'", tag),
+            PgnError::InvalidFen(error) => write!(f, "invalid FEN tag: {}", error),
+            PgnError::IllegalMove { san, fullmove_number, white } => {
+                write!(f, "illegal move {}{} {}", fullmove_number, if *white { "." } else { "..." }, san)
+            }
+            PgnError::UnterminatedComment => write!(f, "comment is never closed"),
+            PgnError::UnterminatedVariation => write!(f, "variation is never closed"),
+            PgnError::UnexpectedClose => write!(f, "')' without a variation to close"),
+            PgnError::VariationWithoutMove => write!(f, "variation before any move"),
+            PgnError::UnexpectedCharacter(c) => write!(f, "unexpected character '{}'", c),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+// a move of the game tree, children[0] continuing the line it belongs to and the
+// other children being variations of that continuation
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveNode {
+    pub mv: Move,
+    pub san: String,
+    pub nags: Vec<u8>,
+    // comments following the move, or opening the variation it starts
+    pub comments: Vec<String>,
+    pub children: Vec<MoveNode>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub start: Position,
+    // comments before the first move
+    pub comments: Vec<String>,
+    // the first move of the main line and its alternatives
+    pub moves: Vec<MoveNode>,
+    pub result: String,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+    }
+
+    // the moves available after following path, path[i] picking among the children at depth i
+    pub fn children(&self, path: &[usize]) -> Option<&[MoveNode]> {
+        let mut children = &self.moves;
+
+        for i in path {
+            children = &children.get(*i)?.children;
+        }
+        Some(children)
+    }
+
+    pub fn node(&self, path: &[usize]) -> Option<&MoveNode> {
+        let (last, parent) = path.split_last()?;
+
+        self.children(parent)?.get(*last)
+    }
+
+    pub fn position_at(&self, path: &[usize]) -> Option<Position> {
+        let mut position = self.start.clone();
+        let mut children = &self.moves;
+
+        for i in path {
+            let node = children.get(*i)?;
+            position.play(node.mv);
+            children = &node.children;
+        }
+        Some(position)
+    }
+
+    // the main line alone, as if it had been played on the board
+    pub fn main_line(&self) -> Game {
+        let mut game = Game::new(self.start.clone());
+        let mut position = self.start.clone();
+        let mut children = &self.moves;
+
+        while let Some(node) = children.first() {
+            game.record(&position, node.mv, None);
+            position.play(node.mv);
+            children = &node.children;
+        }
+        game
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Nag(u8),
+    Open,
+    Close,
+    Result(String),
+    Move(String),
+    // kept in the stream so only the game it belongs to fails
+    Invalid(PgnError),
+}
+
+fn is_result(symbol: &str) -> bool {
+    matches!(symbol, "1-0" | "0-1" | "1/2-1/2" | "*")
+}
+
+// "!" and "?" suffixes as their numeric annotation glyphs
+fn suffix_nag(suffix: &str) -> Option<u8> {
+    match suffix {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
+
+fn parse_tag(tag: &str) -> Option<(String, String)> {
+    let tag = tag.trim();
+    let (name, value) = tag.split_once(char::is_whitespace)?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+    Some((name.to_string(), value.replace("\\\"", "\"").replace("\\\\", "\\")))
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    // collects chars from i while the predicate holds
+    let take_while = |i: &mut usize, predicate: &dyn Fn(char) -> bool| {
+        let start = *i;
+        while *i < chars.len() && predicate(chars[*i]) {
+            *i += 1;
+        }
+        chars[start..*i].iter().collect::<String>()
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let line_start = i == 0 || chars[i - 1] == '\n';
+
+        match c {
+            _ if c.is_whitespace() => i += 1,
+            // escaped lines are for other programs
+            '%' if line_start => _ = take_while(&mut i, &|c| c != '\n'),
+            ';' => {
+                i += 1;
+                tokens.push(Token::Comment(take_while(&mut i, &|c| c != '\n').trim().to_string()));
+            }
+            '{' => {
+                i += 1;
+                let comment = take_while(&mut i, &|c| c != '}');
+                if i == chars.len() {
+                    tokens.push(Token::Invalid(PgnError::UnterminatedComment));
+                }
+                i += 1;
+                tokens.push(Token::Comment(comment.split_whitespace().collect::<Vec<_>>().join(" ")));
+            }
+            '[' => {
+                i += 1;
+                let start = i;
+                let mut in_string = false;
+                let mut escaped = false;
+                while i < chars.len() && (in_string || chars[i] != ']') && chars[i] != '\n' {
+                    if escaped {
+                        escaped = false;
+                    } else if chars[i] == '\\' {
+                        escaped = true;
+                    } else if chars[i] == '"' {
+                        in_string = !in_string;
+                    }
+                    i += 1;
+                }
+                let tag: String = chars[start..i].iter().collect();
+                let closed = chars.get(i) == Some(&']');
+                i += 1;
+                tokens.push(match parse_tag(&tag) {
+                    Some((name, value)) if closed => Token::Tag(name, value),
+                    _ => Token::Invalid(PgnError::InvalidTag(tag)),
+                });
+            }
+            '(' => {
+                i += 1;
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                i += 1;
+                tokens.push(Token::Close);
+            }
+            '*' => {
+                i += 1;
+                tokens.push(Token::Result("*".to_string()));
+            }
+            '$' => {
+                i += 1;
+                match take_while(&mut i, &|c| c.is_ascii_digit()).parse() {
+                    Ok(nag) => tokens.push(Token::Nag(nag)),
+                    Err(_) => tokens.push(Token::Invalid(PgnError::UnexpectedCharacter('$'))),
+                }
+            }
+            '!' | '?' => {
+                let suffix = take_while(&mut i, &|c| c == '!' || c == '?');
+                match suffix_nag(&suffix) {
+                    Some(nag) => tokens.push(Token::Nag(nag)),
+                    None => tokens.push(Token::Invalid(PgnError::UnexpectedCharacter(c))),
+                }
+            }
+            // the "e.p." written after an en passant capture adds nothing to the move before it
+            'e' if chars[i..].starts_with(&['e', '.', 'p', '.']) => i += 4,
+            _ if c.is_ascii_alphanumeric() => {
+                let symbol = take_while(&mut i, &|c| c.is_ascii_alphanumeric() || "-/+#=:_".contains(c));
+
+                // move numbers such as "12." or "12..." carry nothing the position does not know
+                if symbol.chars().all(|c| c.is_ascii_digit()) && chars.get(i) == Some(&'.') {
+                    take_while(&mut i, &|c| c == '.');
+                } else if is_result(&symbol) {
+                    tokens.push(Token::Result(symbol));
+                } else {
+                    tokens.push(Token::Move(symbol));
+                }
+            }
+            // stray dots between a move number and its move
+            '.' => i += 1,
+            _ => {
+                i += 1;
+                tokens.push(Token::Invalid(PgnError::UnexpectedCharacter(c)));
+            }
+        }
+    }
+    tokens
+}
+
+// a move as written, before it is checked against the position
+struct ParsedMove {
+    san: String,
+    nags: Vec<u8>,
+    comments: Vec<String>,
+    // lines replacing this move
+    variations: Vec<Vec<ParsedMove>>,
+}
+
+// reads moves up to the end of the variation, or of the game when nested is false;
+// comments before the first move of the line are returned on their own
+fn parse_line(tokens: &[Token], i: &mut usize, nested: bool) -> Result<(Vec<String>, Vec<ParsedMove>), PgnError> {
+    let mut leading_comments = Vec::new();
+    let mut line: Vec<ParsedMove> = Vec::new();
+
+    while let Some(token) = tokens.get(*i) {
+        *i += 1;
+        match token {
+            Token::Move(san) => line.push(ParsedMove { san: san.clone(), nags: Vec::new(), comments: Vec::new(), variations: Vec::new() }),
+            Token::Comment(comment) => match line.last_mut() {
+                Some(last) => last.comments.push(comment.clone()),
+                None => leading_comments.push(comment.clone()),
+            },
+            Token::Nag(nag) => {
+                if let Some(last) = line.last_mut() {
+                    last.nags.push(*nag);
+                }
+            }
+            Token::Open => {
+                let (comments, mut variation) = parse_line(tokens, i, true)?;
+                let Some(last) = line.last_mut() else {
+                    return Err(PgnError::VariationWithoutMove);
+                };
+                if let Some(first) = variation.first_mut() {
+                    first.comments.splice(0..0, comments);
+                }
+                last.variations.push(variation);
+            }
+            Token::Close if nested => return Ok((leading_comments, line)),
+            Token::Close => return Err(PgnError::UnexpectedClose),
+            Token::Invalid(error) => return Err(error.clone()),
+            Token::Result(_) | Token::Tag(..) => {
+                *i -= 1;
+                break;
+            }
+        }
+    }
+
+    if nested {
+        return Err(PgnError::UnterminatedVariation);
+    }
+    Ok((leading_comments, line))
+}
+
+// the nodes played from position: the line's first move followed by the first moves of its variations
+fn build_line(position: &Position, line: &[ParsedMove]) -> Result<Vec<MoveNode>, PgnError> {
+    let Some((first, rest)) = line.split_first() else {
+        return Ok(Vec::new());
+    };
+
+    let mv = parse_san(position, &first.san).ok_or(PgnError::IllegalMove {
+        san: first.san.clone(),
+        fullmove_number: position.fullmove_number,
+        white: position.is_white_turn,
+    })?;
+    let mut after = position.clone();
+    after.play(mv);
+
+    let mut nodes = vec![MoveNode {
+        mv,
+        san: to_san(position, &mv),
+        nags: first.nags.clone(),
+        comments: first.comments.clone(),
+        children: build_line(&after, rest)?,
+    }];
+    for variation in &first.variations {
+        nodes.extend(build_line(position, variation)?);
+    }
+    Ok(nodes)
+}
+
+// one game from its first tag, the rest of the tokens are left for the next game
+fn parse_game(tokens: &[Token], i: &mut usize) -> Result<PgnGame, PgnError> {
+    let mut tags = Vec::new();
+    while let Some(Token::Tag(name, value)) = tokens.get(*i) {
+        tags.push((name.clone(), value.clone()));
+        *i += 1;
+    }
+
+    let line = parse_line(tokens, i, false);
+    let result = match tokens.get(*i) {
+        Some(Token::Result(result)) => {
+            *i += 1;
+            Some(result.clone())
+        }
+        _ => None,
+    };
+    let (comments, line) = line?;
+
+    let mut start = match tags.iter().find(|(name, _)| name == "FEN") {
+        Some((_, fen)) => translate_fen(fen).map_err(PgnError::InvalidFen)?,
+        None => translate_fen(STARTING_FEN).unwrap(),
+    };
+    if let Some((_, variant)) = tags.iter().find(|(name, _)| name == "Variant") {
+        start.chess960 |= ["chess960", "chess 960", "fischerandom", "fischer random"].contains(&variant.to_lowercase().as_str());
+    }
+    let moves = build_line(&start, &line)?;
+    let result = result
+        .or(tags.iter().find(|(name, _)| name == "Result").map(|(_, value)| value.clone()))
+        .unwrap_or("*".to_string());
+
+    Ok(PgnGame { tags, start, comments, moves, result })
+}
+
+// every game of a PGN file, one failing does not stop the ones after it from loading
+pub fn read_pgn(text: &str) -> Vec<Result<PgnGame, PgnError>> {
+    let tokens = tokenize(text);
+    let mut games = Vec::new();
+    let mut i = 0;
+    let starts_game = |i: usize| matches!(tokens[i], Token::Tag(..)) && !matches!(tokens[i - 1], Token::Tag(..));
+
+    while i < tokens.len() {
+        let start = i;
+        games.push(parse_game(&tokens, &mut i));
+
+        // after an error, skip to the tags of the next game
+        if games.last().unwrap().is_err() || i == start {
+            i = start + 1;
+            while i < tokens.len() && !starts_game(i) {
+                i += 1;
+            }
+        }
+    }
+    games
+}
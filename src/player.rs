@@ -0,0 +1,238 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::bot::{self, time_for_move, Limits};
+use crate::chess::{Move, Position};
+use crate::clock::Clock;
+use crate::fen::{to_fen, STARTING_FEN};
+use crate::game::Game;
+use crate::uci::find_move;
+
+// how long an engine may take over its time budget before it is told to stop, and again before it is given up on
+const GRACE_TIME: Duration = Duration::from_secs(1);
+// for getting through the handshake
+const STARTUP_TIME: Duration = Duration::from_secs(10);
+// how often a wait for the engine looks at the stop flag
+const POLL_TIME: Duration = Duration::from_millis(10);
+
+// "position startpos moves e2e4 e7e5", or "position fen <start> moves ..." from any other start,
+// so the engine knows the positions that came before and can tell repetitions
+pub fn position_command(game: &Game) -> String {
+    let start = to_fen(&game.start);
+    let mut command = if start == STARTING_FEN { "position startpos".to_string() } else { format!("position fen {}", start) };
+
+    if !game.moves.is_empty() {
+        command += " moves";
+        for game_move in &game.moves {
+            command += " ";
+            command += &game_move.mv.name();
+        }
+    }
+    command
+}
+
+// whoever picks the moves of the side the human does not play
+pub enum Player {
+    Bot(Limits),
+    Uci(UciEngine),
+}
+
+impl Player {
+    pub fn name(&self) -> &str {
+        match self {
+            Player::Bot(_) => "bot",
+            Player::Uci(engine) => &engine.name,
+        }
+    }
+
+    // the move to play after the game's moves; with a clock running the time to think comes off it
+    // instead of the fixed time limit, and setting stop cuts the search short
+    pub fn choose_move(&mut self, game: &Game, clock: Option<&Clock>, stop: &AtomicBool) -> io::Result<Option<Move>> {
+        match self {
+            Player::Bot(limits) => {
+                let position = game.position();
+                let mut limits = *limits;
+                if let Some(clock) = clock {
+                    let white = position.is_white_turn;
+                    let moves_to_go = clock.moves_to_go(white).unwrap_or(30);
+                    limits.time = Some(time_for_move(clock.time_left(white, Instant::now()), clock.increment(white), moves_to_go));
+                }
+                Ok(bot::search_until(&position, &limits, stop, |_| ()).best_move())
+            }
+            Player::Uci(engine) => engine.best_move(game, clock, stop),
+        }
+    }
+}
+
+// a UCI engine executable run as a child process, asked for a move with the same limits as the bot
+pub struct UciEngine {
+    child: Child,
+    input: ChildStdin,
+    // the engine's lines, read on a thread of their own so waiting for them can time out
+    output: Receiver<String>,
+    limits: Limits,
+    pub name: String,
+    // whether UCI_Chess960 was turned on, so castling comes back as the king taking its rook
+    chess960: bool,
+}
+
+impl UciEngine {
+    pub fn start(path: &str, limits: Limits) -> io::Result<UciEngine> {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let input = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let (sender, output) = mpsc::channel();
+        thread::spawn(move || {
+            for line in stdout.lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = UciEngine { child, input, output, limits, name: path.to_string(), chess960: false };
+
+        engine.send("uci")?;
+        let id = engine.wait_for("uciok", Some(Instant::now() + STARTUP_TIME), &AtomicBool::new(false))?;
+        if let Some(name) = id.iter().find_map(|line| line.strip_prefix("id name ")) {
+            engine.name = name.to_string();
+        }
+        engine.send("ucinewgame")?;
+        engine.send("isready")?;
+        engine.wait_for("readyok", Some(Instant::now() + STARTUP_TIME), &AtomicBool::new(false))?;
+
+        Ok(engine)
+    }
+
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.input, "{}", command)?;
+        self.input.flush()
+    }
+
+    // reads up to the line starting with prefix and returns every line before it, a TimedOut error
+    // once the deadline passes and an Interrupted one once stop is set
+    fn wait_for(&mut self, prefix: &str, deadline: Option<Instant>, stop: &AtomicBool) -> io::Result<Vec<String>> {
+        let mut lines = Vec::new();
+
+        loop {
+            let wait = deadline.map_or(POLL_TIME, |deadline| deadline.saturating_duration_since(Instant::now()).min(POLL_TIME));
+            let line = match self.output.recv_timeout(wait) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => {
+                    if stop.load(Ordering::Relaxed) {
+                        return Err(io::Error::new(io::ErrorKind::Interrupted, format!("{} was stopped before sending {}", self.name, prefix)));
+                    }
+                    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        return Err(io::Error::new(io::ErrorKind::TimedOut, format!("{} did not send {} in time", self.name, prefix)));
+                    }
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("{} exited before sending {}", self.name, prefix)));
+                }
+            };
+            let line = line.trim();
+            if line.starts_with(prefix) {
+                lines.push(line.to_string());
+                return Ok(lines);
+            }
+            lines.push(line.to_string());
+        }
+    }
+
+    // the engine shares out the time on the clock itself, a Bronstein delay passed on as an increment
+    fn go_command(&self, position: &Position, clock: Option<&Clock>) -> String {
+        let mut command = "go".to_string();
+
+        if let Some(depth) = self.limits.depth {
+            command += &format!(" depth {}", depth);
+        }
+        match clock {
+            Some(clock) => {
+                let now = Instant::now();
+                command += &format!(
+                    " wtime {} btime {} winc {} binc {}",
+                    clock.time_left(true, now).as_millis(),
+                    clock.time_left(false, now).as_millis(),
+                    clock.increment(true).as_millis(),
+                    clock.increment(false).as_millis()
+                );
+                if let Some(moves_to_go) = clock.moves_to_go(position.is_white_turn) {
+                    command += &format!(" movestogo {}", moves_to_go);
+                }
+            }
+            None => {
+                if let Some(time) = self.limits.time {
+                    command += &format!(" movetime {}", time.as_millis());
+                }
+            }
+        }
+        command
+    }
+
+    // the most the engine should think: what is left on its clock, or the move time; None when only the depth is limited
+    fn time_budget(&self, position: &Position, clock: Option<&Clock>) -> Option<Duration> {
+        match clock {
+            Some(clock) => Some(clock.time_left(position.is_white_turn, Instant::now())),
+            None => self.limits.time,
+        }
+    }
+
+    // None when the engine has no move to play, an error when it answers with an illegal one or,
+    // once over its time budget or stopped, does not answer even after being told to stop
+    pub fn best_move(&mut self, game: &Game, clock: Option<&Clock>, stop: &AtomicBool) -> io::Result<Option<Move>> {
+        let position = game.position();
+        if position.chess960 != self.chess960 {
+            self.send(&format!("setoption name UCI_Chess960 value {}", position.chess960))?;
+            self.chess960 = position.chess960;
+        }
+        self.send(&position_command(game))?;
+        self.send(&self.go_command(&position, clock))?;
+
+        let deadline = self.time_budget(&position, clock).map(|budget| Instant::now() + budget + GRACE_TIME);
+        let lines = match self.wait_for("bestmove", deadline, stop) {
+            // the bestmove the engine still owes is read here, so it is not taken for the answer to the next go
+            Err(error) if matches!(error.kind(), io::ErrorKind::TimedOut | io::ErrorKind::Interrupted) => {
+                self.send("stop")?;
+                self.wait_for("bestmove", Some(Instant::now() + GRACE_TIME), &AtomicBool::new(false))?
+            }
+            lines => lines?,
+        };
+        let name = lines.last().unwrap().split_whitespace().nth(1).unwrap_or("0000");
+        if name == "0000" || name == "(none)" {
+            return Ok(None);
+        }
+
+        match find_move(&position, name) {
+            Some(mv) => Ok(Some(mv)),
+            None => Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} played the illegal move {}", self.name, name))),
+        }
+    }
+}
+
+// an engine that does not quit within the grace time is killed, so it cannot keep the GUI from closing
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        _ = self.send("quit");
+
+        let deadline = Instant::now() + GRACE_TIME;
+        while let Ok(None) = self.child.try_wait() {
+            if Instant::now() >= deadline {
+                _ = self.child.kill();
+                break;
+            }
+            thread::sleep(POLL_TIME);
+        }
+        _ = self.child.wait();
+    }
+}
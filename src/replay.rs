@@ -0,0 +1,98 @@
+use crate::chess::Position;
+use crate::pgn::{MoveNode, PgnGame};
+
+// where a PGN file is being looked at: the game, and the child taken at every move of its tree
+pub struct Replay {
+    pub games: Vec<PgnGame>,
+    pub game: usize,
+    pub path: Vec<usize>,
+}
+
+impl Replay {
+    pub fn new(games: Vec<PgnGame>) -> Replay {
+        Replay { games, game: 0, path: Vec::new() }
+    }
+
+    pub fn current(&self) -> &PgnGame {
+        &self.games[self.game]
+    }
+
+    pub fn position(&self) -> Position {
+        self.current().position_at(&self.path).unwrap()
+    }
+
+    // the move that led to the position shown, None at the start of the game
+    pub fn last_move(&self) -> Option<&MoveNode> {
+        self.current().node(&self.path)
+    }
+
+    // follows the line the current move belongs to
+    pub fn forward(&mut self) -> bool {
+        let has_next = !self.current().children(&self.path).unwrap().is_empty();
+
+        if has_next {
+            self.path.push(0);
+        }
+        has_next
+    }
+
+    pub fn back(&mut self) -> bool {
+        self.path.pop().is_some()
+    }
+
+    pub fn to_start(&mut self) {
+        self.path.clear();
+    }
+
+    pub fn to_end(&mut self) {
+        while self.forward() {}
+    }
+
+    // moves back, or forward along the current line, until plies moves were played
+    pub fn go_to(&mut self, plies: usize) -> bool {
+        let before = self.path.clone();
+
+        self.path.truncate(plies);
+        while self.path.len() < plies && self.forward() {}
+        self.path != before
+    }
+
+    // the moves leading to the position shown, then the rest of the line it is on
+    pub fn line(&self) -> Vec<&MoveNode> {
+        let game = self.current();
+        let mut path = self.path.clone();
+        let mut nodes: Vec<&MoveNode> = (1..=path.len()).map(|plies| game.node(&path[..plies]).unwrap()).collect();
+
+        while let Some(next) = game.children(&path).unwrap().first() {
+            nodes.push(next);
+            path.push(0);
+        }
+        nodes
+    }
+
+    // swaps the last move for the next (or previous) alternative played from the same position
+    pub fn switch_variation(&mut self, forward: bool) -> bool {
+        let Some((last, parent)) = self.path.split_last() else {
+            return false;
+        };
+        let count = self.current().children(parent).unwrap().len();
+        if count < 2 {
+            return false;
+        }
+
+        let last = if forward { (last + 1) % count } else { (last + count - 1) % count };
+        *self.path.last_mut().unwrap() = last;
+        true
+    }
+
+    pub fn switch_game(&mut self, forward: bool) -> bool {
+        let game = if forward { self.game + 1 } else { self.game.wrapping_sub(1) };
+        if game >= self.games.len() {
+            return false;
+        }
+
+        self.game = game;
+        self.path.clear();
+        true
+    }
+}
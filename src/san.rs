@@ -0,0 +1,160 @@
+use crate::chess::{file_of, rank_of, square_from_name, square_name, Move, MoveFlag, Outcome, Pieces, Position};
+
+pub fn piece_letter(piece: Pieces) -> &'static str {
+    match piece {
+        Pieces::PAWN => "",
+        Pieces::BISH => "B",
+        Pieces::KNIG => "N",
+        Pieces::ROOK => "R",
+        Pieces::QUEE => "Q",
+        Pieces::KING => "K",
+    }
+}
+
+// the file, the rank or the whole square of the origin, whichever first tells the move apart
+// from the same piece type reaching the same square
+fn disambiguation(position: &Position, mv: &Move) -> String {
+    let rivals: Vec<usize> = position
+        .legal_move_list()
+        .into_iter()
+        .filter(|legal| legal.to == mv.to && legal.from != mv.from && legal.piece == mv.piece)
+        .map(|legal| legal.from)
+        .collect();
+
+    let square = square_name(mv.from);
+    if rivals.is_empty() {
+        String::new()
+    } else if rivals.iter().all(|from| file_of(*from) != file_of(mv.from)) {
+        square[0..1].to_string()
+    } else if rivals.iter().all(|from| rank_of(*from) != rank_of(mv.from)) {
+        square[1..2].to_string()
+    } else {
+        square
+    }
+}
+
+// Standard Algebraic Notation of a legal move, e.g. "Nbd7", "exd6", "O-O-O", "e8=Q+" or "Qh4#"
+pub fn to_san(position: &Position, mv: &Move) -> String {
+    let piece_type = Pieces::from_piece(mv.piece).unwrap_or(Pieces::PAWN);
+
+    let mut san = match mv.flag {
+        MoveFlag::Castle if mv.to > mv.from => "O-O".to_string(),
+        MoveFlag::Castle => "O-O-O".to_string(),
+        _ => {
+            let mut san = piece_letter(piece_type).to_string();
+
+            if piece_type == Pieces::PAWN {
+                if mv.is_capture() {
+                    san += &square_name(mv.from)[0..1];
+                }
+            } else {
+                san += &disambiguation(position, mv);
+            }
+            if mv.is_capture() {
+                san += "x";
+            }
+            san += &square_name(mv.to);
+            if let Some(promotion) = mv.promotion {
+                san += "=";
+                san += piece_letter(promotion);
+            }
+            san
+        }
+    };
+
+    let mut after = position.clone();
+    after.play(*mv);
+    match after.outcome() {
+        Some(Outcome::Checkmate { .. }) => san += "#",
+        _ if after.in_check() => san += "+",
+        _ => (),
+    }
+    san
+}
+
+// SAN as shown to players, en passant captures spelled out as in "exd6 e.p.";
+// files and engines expect the plain to_san form
+pub fn to_display_san(position: &Position, mv: &Move) -> String {
+    let san = to_san(position, mv);
+
+    if mv.flag == MoveFlag::EnPassant {
+        return san + " e.p.";
+    }
+    san
+}
+
+// "12." before a white move, "12..." before a black one
+pub fn move_number(position: &Position) -> String {
+    format!("{}{}", position.fullmove_number, if position.is_white_turn { "." } else { "..." })
+}
+
+fn piece_from_letter(letter: char) -> Option<Pieces> {
+    match letter {
+        'B' => Some(Pieces::BISH),
+        'N' => Some(Pieces::KNIG),
+        'R' => Some(Pieces::ROOK),
+        'Q' => Some(Pieces::QUEE),
+        'K' => Some(Pieces::KING),
+        _ => None,
+    }
+}
+
+// the legal move a SAN string stands for, None when it is illegal, ambiguous or not SAN at all;
+// check, annotation and "e.p." suffixes are ignored and castling may be written with zeros
+pub fn parse_san(position: &Position, san: &str) -> Option<Move> {
+    let san = san.trim();
+    let san = san.strip_suffix("e.p.").map(str::trim_end).unwrap_or(san);
+    let san = san.trim_end_matches(['+', '#', '!', '?']);
+    let legal = position.legal_move_list();
+
+    if san == "O-O" || san == "0-0" || san == "O-O-O" || san == "0-0-0" {
+        let king_side = san.len() == 3;
+        return legal.into_iter().find(|legal| legal.flag == MoveFlag::Castle && (legal.to > legal.from) == king_side);
+    }
+
+    let mut chars: Vec<char> = san.chars().filter(|c| *c != 'x' && *c != '-').collect();
+    let piece = match chars.first().copied().and_then(piece_from_letter) {
+        Some(piece) => {
+            chars.remove(0);
+            piece
+        }
+        None => Pieces::PAWN,
+    };
+
+    // "e8=Q" as well as the older "e8Q"
+    let mut promotion = None;
+    if piece == Pieces::PAWN && let Some(letter) = chars.last().copied().and_then(piece_from_letter) {
+        promotion = Some(letter);
+        chars.pop();
+        if chars.last() == Some(&'=') {
+            chars.pop();
+        }
+    }
+
+    if chars.len() < 2 {
+        return None;
+    }
+    let index = square_from_name(&chars[chars.len() - 2..].iter().collect::<String>())?;
+    let (file, rank) = match &chars[..chars.len() - 2] {
+        [] => (None, None),
+        [c] if c.is_ascii_lowercase() => (Some(*c as i32 - 'a' as i32), None),
+        [c] if c.is_ascii_digit() => (None, Some('8' as i32 - *c as i32)),
+        [f, r] => (Some(*f as i32 - 'a' as i32), Some('8' as i32 - *r as i32)),
+        _ => return None,
+    };
+
+    let mut candidates = legal.into_iter().filter(|legal| {
+        legal.to == index
+            && legal.promotion == promotion
+            && Pieces::from_piece(legal.piece) == Some(piece)
+            && legal.flag != MoveFlag::Castle
+            && file.is_none_or(|file| file == file_of(legal.from))
+            && rank.is_none_or(|rank| rank == rank_of(legal.from))
+    });
+
+    let mv = candidates.next()?;
+    if candidates.next().is_some() {
+        return None;
+    }
+    Some(mv)
+}
@@ -0,0 +1,214 @@
+use std::io::{BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::bot::{self, time_for_move, Limits, SearchInfo, MATE};
+use crate::chess::{parse_move_name, Move, Position};
+use crate::fen::{translate_fen, STARTING_FEN};
+
+// the legal move of the side to move written as "e2e4" or "e7e8q"
+pub fn find_move(position: &Position, name: &str) -> Option<Move> {
+    let (from, to, promotion) = parse_move_name(name)?;
+
+    position.find_move(from, to, promotion)
+}
+
+// "cp 35", or "mate 3" / "mate -2" counted in moves rather than plies
+pub fn score_string(score: i32) -> String {
+    if score >= MATE - 1000 {
+        format!("mate {}", (MATE - score + 1) / 2)
+    } else if score <= -MATE + 1000 {
+        format!("mate -{}", (MATE + score) / 2)
+    } else {
+        format!("cp {}", score)
+    }
+}
+
+pub fn info_string(info: &SearchInfo) -> String {
+    let pv: Vec<String> = info.pv.iter().map(Move::name).collect();
+
+    format!(
+        "info depth {} score {} nodes {} time {} pv {}",
+        info.depth,
+        score_string(info.score),
+        info.nodes,
+        info.time.as_millis(),
+        pv.join(" ")
+    )
+}
+
+// applies "startpos moves e2e4 e7e5" or "fen <six fields> moves ...", castling moves being written
+// as the king taking its own rook, "e1h1", in Chess960
+pub fn parse_position(args: &[&str], chess960: bool) -> Result<Position, String> {
+    let moves_at = args.iter().position(|arg| *arg == "moves").unwrap_or(args.len());
+
+    let mut position = match args.first() {
+        Some(&"startpos") => translate_fen(STARTING_FEN).unwrap(),
+        Some(&"fen") => translate_fen(&args[1..moves_at].join(" ")).map_err(|error| error.to_string())?,
+        _ => return Err("position expects startpos or fen".to_string()),
+    };
+    position.chess960 |= chess960;
+
+    for name in args.iter().skip(moves_at + 1) {
+        let mv = find_move(&position, name).ok_or(format!("illegal move {}", name))?;
+        position.play(mv);
+    }
+
+    Ok(position)
+}
+
+// the searching limits of a "go" command, infinite being reported on its own since it ignores every limit
+pub fn parse_go(args: &[&str], white_to_move: bool) -> (Limits, bool) {
+    let mut limits = Limits { depth: None, time: None };
+    let mut clock: Option<u64> = None;
+    let mut increment: u64 = 0;
+    let mut moves_to_go: u32 = 30;
+    let mut infinite = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().and_then(|value| value.parse::<u64>().ok()).unwrap_or(0);
+
+        match *arg {
+            "depth" => limits.depth = Some(value() as u32),
+            "movetime" => limits.time = Some(Duration::from_millis(value())),
+            "wtime" if white_to_move => clock = Some(value()),
+            "btime" if !white_to_move => clock = Some(value()),
+            "winc" if white_to_move => increment = value(),
+            "binc" if !white_to_move => increment = value(),
+            "wtime" | "btime" | "winc" | "binc" => _ = value(),
+            "movestogo" => moves_to_go = value() as u32,
+            "infinite" => infinite = true,
+            _ => (),
+        }
+    }
+
+    if let Some(clock) = clock && limits.time.is_none() {
+        limits.time = Some(time_for_move(Duration::from_millis(clock), Duration::from_millis(increment), moves_to_go));
+    }
+    if infinite {
+        limits = Limits { depth: None, time: None };
+    }
+
+    (limits, infinite)
+}
+
+pub fn send<W: Write>(output: &Mutex<W>, line: &str) {
+    let mut output = output.lock().unwrap();
+    _ = writeln!(output, "{}", line);
+    _ = output.flush();
+}
+
+struct Engine<W: Write + Send + 'static> {
+    position: Position,
+    output: Arc<Mutex<W>>,
+    stop: Arc<AtomicBool>,
+    search: Option<JoinHandle<()>>,
+    infinite: bool,
+    chess960: bool,
+}
+
+impl<W: Write + Send + 'static> Engine<W> {
+    fn wait(&mut self) {
+        if let Some(search) = self.search.take() {
+            _ = search.join();
+        }
+    }
+
+    fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.wait();
+    }
+
+    fn go(&mut self, args: &[&str]) {
+        self.stop();
+        self.stop.store(false, Ordering::Relaxed);
+
+        let (limits, infinite) = parse_go(args, self.position.is_white_turn);
+        self.infinite = infinite;
+        let position = self.position.clone();
+        let output = Arc::clone(&self.output);
+        let stop = Arc::clone(&self.stop);
+
+        self.search = Some(thread::spawn(move || {
+            let info = bot::search_until(&position, &limits, &stop, |info| send(&output, &info_string(info)));
+
+            // an infinite search only answers once it is told to stop
+            while infinite && !stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(5));
+            }
+
+            let best_move = info.best_move().map(|mv| mv.name()).unwrap_or("0000".to_string());
+            send(&output, &format!("bestmove {}", best_move));
+        }));
+    }
+}
+
+// reads commands until "quit" or the end of the input, a search still running at the end is let finish
+pub fn run<R: BufRead, W: Write + Send + 'static>(input: R, output: W) {
+    let mut engine = Engine {
+        position: translate_fen(STARTING_FEN).unwrap(),
+        output: Arc::new(Mutex::new(output)),
+        stop: Arc::new(AtomicBool::new(false)),
+        search: None,
+        infinite: false,
+        chess960: false,
+    };
+
+    for line in input.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((command, args)) = words.split_first() else {
+            continue;
+        };
+
+        match *command {
+            "uci" => {
+                send(&engine.output, "id name ChessRust");
+                send(&engine.output, "id author Pad");
+                send(&engine.output, "option name UCI_Chess960 type check default false");
+                send(&engine.output, "uciok");
+            }
+            "isready" => send(&engine.output, "readyok"),
+            "setoption" => {
+                // setoption name <id> [value <x>]
+                let value_at = args.iter().position(|arg| *arg == "value").unwrap_or(args.len());
+                let name = args.get(1..value_at).unwrap_or_default().join(" ");
+                let value = args.get(value_at + 1..).unwrap_or_default().join(" ");
+
+                if name.eq_ignore_ascii_case("UCI_Chess960") {
+                    engine.chess960 = value == "true";
+                } else {
+                    send(&engine.output, &format!("info string unknown option {}", name));
+                }
+            }
+            "ucinewgame" => {
+                engine.stop();
+                engine.position = translate_fen(STARTING_FEN).unwrap();
+            }
+            "position" => {
+                engine.stop();
+                match parse_position(args, engine.chess960) {
+                    Ok(position) => engine.position = position,
+                    Err(message) => send(&engine.output, &format!("info string {}", message)),
+                }
+            }
+            "go" => engine.go(args),
+            "stop" => engine.stop(),
+            "quit" => {
+                engine.stop();
+                return;
+            }
+            _ => send(&engine.output, &format!("info string unknown command {}", command)),
+        }
+    }
+
+    if engine.infinite {
+        engine.stop();
+    }
+    engine.wait();
+}
@@ -0,0 +1,293 @@
+use std::io::{BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::bot::{self, time_for_move, Limits, SearchInfo, MATE};
+use crate::chess::{Move, Outcome, Position};
+use crate::fen::{translate_fen, STARTING_FEN};
+use crate::uci::{find_move, send};
+
+// "1-0 {White mates}" style line announcing the end of the game
+pub fn result_string(outcome: &Outcome) -> String {
+    match outcome {
+        Outcome::Checkmate { white_wins: true } => "1-0 {White mates}".to_string(),
+        Outcome::Checkmate { white_wins: false } => "0-1 {Black mates}".to_string(),
+        Outcome::Stalemate => "1/2-1/2 {Stalemate}".to_string(),
+        Outcome::ThreefoldRepetition | Outcome::FivefoldRepetition => "1/2-1/2 {Draw by repetition}".to_string(),
+        Outcome::FiftyMoveRule | Outcome::SeventyFiveMoveRule => "1/2-1/2 {Draw by fifty-move rule}".to_string(),
+        Outcome::InsufficientMaterial => "1/2-1/2 {Insufficient material}".to_string(),
+        Outcome::Timeout { white_wins: true } => "1-0 {Black's flag fell}".to_string(),
+        Outcome::Timeout { white_wins: false } => "0-1 {White's flag fell}".to_string(),
+        Outcome::TimeoutVsInsufficientMaterial => "1/2-1/2 {Timeout vs insufficient material}".to_string(),
+    }
+}
+
+// the engine claims draws as soon as it may
+fn game_end(position: &Position) -> Option<Outcome> {
+    position.outcome().or_else(|| position.draw_claim())
+}
+
+// centipawns, or 100000 + n when mating in n moves as the protocol suggests
+pub fn score_value(score: i32) -> i32 {
+    if score >= MATE - 1000 {
+        100_000 + (MATE - score + 1) / 2
+    } else if score <= -MATE + 1000 {
+        -100_000 - (MATE + score) / 2
+    } else {
+        score
+    }
+}
+
+// "ply score time nodes pv" thinking line, time in centiseconds
+pub fn thinking_string(info: &SearchInfo) -> String {
+    let pv: Vec<String> = info.pv.iter().map(Move::name).collect();
+
+    format!(
+        "{} {} {} {} {}",
+        info.depth,
+        score_value(info.score),
+        info.time.as_millis() / 10,
+        info.nodes,
+        pv.join(" ")
+    )
+}
+
+// minutes given as "5" or "5:30"
+fn parse_base_time(base: &str) -> Option<Duration> {
+    let (minutes, seconds) = base.split_once(':').unwrap_or((base, "0"));
+
+    Some(Duration::from_secs(minutes.parse::<u64>().ok()? * 60 + seconds.parse::<u64>().ok()?))
+}
+
+// how long to think when neither level, st nor sd set a limit
+pub const DEFAULT_MOVE_TIME: Duration = Duration::from_secs(1);
+
+// seconds that may have a fraction, as in "level 40 5 0.5"
+pub fn parse_seconds(seconds: &str) -> Option<Duration> {
+    let seconds: f64 = seconds.parse().ok().filter(|seconds: &f64| seconds.is_finite() && *seconds >= 0.0)?;
+
+    Some(Duration::from_secs_f64(seconds))
+}
+
+struct Engine<W: Write + Send + 'static> {
+    position: Position,
+    history: Vec<Position>,
+    // the side the engine plays, None in force mode
+    engine_side: Option<bool>,
+    depth: Option<u32>,
+    move_time: Option<Duration>,
+    clock: Option<Duration>,
+    increment: Duration,
+    moves_per_session: u32,
+    post: bool,
+    output: Arc<Mutex<W>>,
+    stop: Arc<AtomicBool>,
+    cancel: Arc<AtomicBool>,
+    // the search thread hands back the move it announced, None when it was cancelled first
+    search: Option<JoinHandle<Option<Move>>>,
+}
+
+impl<W: Write + Send + 'static> Engine<W> {
+    fn play(&mut self, mv: Move) {
+        self.history.push(self.position.clone());
+        self.position.play(mv);
+    }
+
+    // waits for the search to end and plays the move it sent
+    fn collect(&mut self) {
+        if let Some(search) = self.search.take()
+        && let Ok(Some(mv)) = search.join() {
+            self.play(mv);
+        }
+    }
+
+    // makes the engine move right away with what it has found so far
+    fn move_now(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.collect();
+    }
+
+    // drops the search, unless its move already went out
+    fn cancel(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        self.move_now();
+    }
+
+    fn limits(&self) -> Limits {
+        let time = match (self.move_time, self.clock) {
+            (Some(move_time), _) => Some(move_time),
+            (None, Some(clock)) => {
+                // the side's moves since new or setboard, a FEN's move number says nothing about the session
+                let moves_played = self.history.iter().filter(|position| position.is_white_turn == self.position.is_white_turn).count() as u32;
+                let moves_to_go = if self.moves_per_session > 0 {
+                    self.moves_per_session - moves_played % self.moves_per_session
+                } else {
+                    30
+                };
+                Some(time_for_move(clock, self.increment, moves_to_go))
+            }
+            (None, None) if self.depth.is_none() => Some(DEFAULT_MOVE_TIME),
+            (None, None) => None,
+        };
+
+        Limits { depth: self.depth, time }
+    }
+
+    fn think(&mut self) {
+        self.collect();
+        if self.engine_side != Some(self.position.is_white_turn) || game_end(&self.position).is_some() {
+            return;
+        }
+        self.stop.store(false, Ordering::Relaxed);
+        self.cancel.store(false, Ordering::Relaxed);
+
+        let limits = self.limits();
+        let post = self.post;
+        let mut position = self.position.clone();
+        let output = Arc::clone(&self.output);
+        let stop = Arc::clone(&self.stop);
+        let cancel = Arc::clone(&self.cancel);
+
+        self.search = Some(thread::spawn(move || {
+            let info = bot::search_until(&position, &limits, &stop, |info| {
+                if post {
+                    send(&output, &thinking_string(info));
+                }
+            });
+            let mv = info.best_move()?;
+
+            // checked under the output lock, so a move is either announced and kept or dropped silently
+            let mut output = output.lock().unwrap();
+            if cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+            _ = writeln!(output, "move {}", mv.name());
+
+            position.play(mv);
+            if let Some(outcome) = game_end(&position) {
+                _ = writeln!(output, "{}", result_string(&outcome));
+            }
+            _ = output.flush();
+
+            Some(mv)
+        }));
+    }
+
+    fn user_move(&mut self, name: &str) {
+        self.collect();
+
+        let Some(mv) = find_move(&self.position, name) else {
+            send(&self.output, &format!("Illegal move: {}", name));
+            return;
+        };
+        self.play(mv);
+
+        match game_end(&self.position) {
+            Some(outcome) => send(&self.output, &result_string(&outcome)),
+            None => self.think(),
+        }
+    }
+
+    fn reset(&mut self, position: Position) {
+        self.position = position;
+        self.history.clear();
+    }
+}
+
+// reads commands until "quit" or the end of the input, a search still running at the end is let finish
+pub fn run<R: BufRead, W: Write + Send + 'static>(input: R, output: W) {
+    let mut engine = Engine {
+        position: translate_fen(STARTING_FEN).unwrap(),
+        history: Vec::new(),
+        engine_side: Some(false),
+        depth: None,
+        move_time: None,
+        clock: None,
+        increment: Duration::ZERO,
+        moves_per_session: 0,
+        post: false,
+        output: Arc::new(Mutex::new(output)),
+        stop: Arc::new(AtomicBool::new(false)),
+        cancel: Arc::new(AtomicBool::new(false)),
+        search: None,
+    };
+
+    for line in input.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((command, args)) = words.split_first() else {
+            continue;
+        };
+        let number = |i: usize| args.get(i).and_then(|arg| arg.parse::<u64>().ok());
+
+        match *command {
+            "xboard" | "accepted" | "rejected" | "hard" | "easy" | "random" | "computer" | "name" | "rating" | "ics" | "otim" => (),
+            "protover" => send(&engine.output, "feature myname=\"ChessRust\" setboard=1 usermove=1 ping=1 sigint=0 sigterm=0 colors=0 analyze=0 done=1"),
+            "new" => {
+                engine.cancel();
+                engine.reset(translate_fen(STARTING_FEN).unwrap());
+                engine.engine_side = Some(false);
+                engine.depth = None;
+            }
+            "force" => {
+                engine.cancel();
+                engine.engine_side = None;
+            }
+            "go" => {
+                engine.collect();
+                engine.engine_side = Some(engine.position.is_white_turn);
+                engine.think();
+            }
+            "usermove" => match args.first() {
+                Some(name) => engine.user_move(name),
+                None => send(&engine.output, "Error (missing move): usermove"),
+            },
+            "setboard" => {
+                engine.cancel();
+                match translate_fen(&args.join(" ")) {
+                    Ok(position) => engine.reset(position),
+                    Err(error) => send(&engine.output, &format!("tellusererror Illegal position: {}", error)),
+                }
+            }
+            "undo" | "remove" => {
+                engine.cancel();
+                for _ in 0..if *command == "undo" { 1 } else { 2 } {
+                    if let Some(position) = engine.history.pop() {
+                        engine.position = position;
+                    }
+                }
+            }
+            "level" => {
+                engine.moves_per_session = number(0).unwrap_or(0) as u32;
+                engine.clock = args.get(1).and_then(|base| parse_base_time(base));
+                engine.increment = args.get(2).and_then(|increment| parse_seconds(increment)).unwrap_or(Duration::ZERO);
+                engine.move_time = None;
+            }
+            "st" => engine.move_time = number(0).map(Duration::from_secs),
+            "sd" => engine.depth = number(0).map(|depth| depth as u32),
+            "time" => engine.clock = number(0).map(|centiseconds| Duration::from_millis(centiseconds * 10)),
+            "post" => engine.post = true,
+            "nopost" => engine.post = false,
+            // a move already sent went out under the same lock, so it always comes before the pong
+            "ping" => send(&engine.output, &format!("pong {}", args.first().unwrap_or(&""))),
+            "?" => engine.move_now(),
+            "result" => {
+                engine.cancel();
+                engine.engine_side = None;
+            }
+            "quit" => {
+                engine.cancel();
+                return;
+            }
+            // moves may also come without the usermove prefix
+            _ if find_move(&engine.position, command).is_some() => engine.user_move(command),
+            _ => send(&engine.output, &format!("Error (unknown command): {}", command)),
+        }
+    }
+
+    engine.collect();
+}
@@ -0,0 +1,45 @@
+use crate::chess::CastlingRights;
+
+// one key per piece code and square, then the side, castling and en passant keys
+const KEY_COUNT: usize = 16 * 64 + 1 + 4 + 8;
+
+// splitmix64, run at compile time so the keys are the same on every run
+const fn random_keys(seed: u64) -> [u64; KEY_COUNT] {
+    let mut keys = [0; KEY_COUNT];
+    let mut state = seed;
+
+    let mut i = 0;
+    while i < KEY_COUNT {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        keys[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+}
+
+const KEYS: [u64; KEY_COUNT] = random_keys(0x4368_6573_7352_7573);
+
+// xored in when black is to move
+pub const BLACK_TO_MOVE: u64 = KEYS[16 * 64];
+
+pub fn piece_key(piece: i8, index: usize) -> u64 {
+    KEYS[64 * piece as usize + index]
+}
+
+pub fn castling_key(castling_rights: &CastlingRights) -> u64 {
+    let rights = [
+        castling_rights.white_king_side,
+        castling_rights.white_queen_side,
+        castling_rights.black_king_side,
+        castling_rights.black_queen_side,
+    ];
+
+    (0..4).filter(|i| rights[*i]).fold(0, |key, i| key ^ KEYS[16 * 64 + 1 + i])
+}
+
+pub fn en_passant_key(file: i32) -> u64 {
+    KEYS[16 * 64 + 5 + file as usize]
+}
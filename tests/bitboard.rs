@@ -0,0 +1,63 @@
+use chess_rust::bitboard::{bishop_attacks, bit, rook_attacks, Squares, KING_ATTACKS, KNIGHT_ATTACKS, LIGHT_SQUARES, PAWN_ATTACKS};
+use chess_rust::chess::{parse_move_name, square_from_name, Pieces};
+use chess_rust::fen::translate_fen;
+
+fn squares(names: &[&str]) -> u64 {
+    names.iter().fold(0, |bits, name| bits | bit(square_from_name(name).unwrap()))
+}
+
+fn at(name: &str) -> usize {
+    square_from_name(name).unwrap()
+}
+
+#[test]
+fn leapers_stay_on_the_board() {
+    assert_eq!(KNIGHT_ATTACKS[at("a8")], squares(&["b6", "c7"]));
+    assert_eq!(KNIGHT_ATTACKS[at("e4")].count_ones(), 8);
+    assert_eq!(KING_ATTACKS[at("h1")], squares(&["g1", "g2", "h2"]));
+    assert_eq!(PAWN_ATTACKS[1][at("a2")], squares(&["b3"]));
+    assert_eq!(PAWN_ATTACKS[0][at("e7")], squares(&["d6", "f6"]));
+}
+
+#[test]
+fn sliders_stop_at_the_first_blocker() {
+    let occupied = squares(&["d6", "b4", "d2", "g4"]);
+
+    assert_eq!(rook_attacks(at("d4"), occupied), squares(&["d5", "d6", "c4", "b4", "e4", "f4", "g4", "d3", "d2"]));
+    assert_eq!(bishop_attacks(at("a1"), 0).count_ones(), 7);
+    assert_eq!(bishop_attacks(at("a1"), squares(&["c3"])), squares(&["b2", "c3"]));
+}
+
+#[test]
+fn square_colors() {
+    assert!(LIGHT_SQUARES & squares(&["a8", "h1", "d1", "e4"]) == squares(&["a8", "h1", "d1", "e4"]));
+    assert_eq!(LIGHT_SQUARES & squares(&["a1", "h8", "e1", "d4"]), 0);
+}
+
+#[test]
+fn squares_lists_the_set_bits() {
+    let names: Vec<usize> = Squares(squares(&["h1", "a8", "e4"])).collect();
+
+    assert_eq!(names, vec![at("a8"), at("e4"), at("h1")]);
+}
+
+#[test]
+fn position_keeps_its_bitboards_in_step() {
+    let mut position = translate_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+
+    for name in ["e1g1", "a6e2", "d5e6"] {
+        let (from, to, _) = parse_move_name(name).unwrap();
+        let mv = position.find_move(from, to, None).unwrap();
+        position.play(mv);
+    }
+
+    for (index, piece) in position.board.iter().enumerate() {
+        let occupied = position.occupied() & bit(index) != 0;
+        assert_eq!(occupied, *piece != 0, "square {}", index);
+        if *piece != 0 {
+            assert!(position.pieces[*piece as usize] & bit(index) != 0, "square {}", index);
+        }
+    }
+    assert_eq!(position.king_index(true), Some(at("g1")));
+    assert_eq!(position.pieces_of(Pieces::ROOK, true), squares(&["a1", "f1"]));
+}
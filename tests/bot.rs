@@ -0,0 +1,58 @@
+use std::time::{Duration, Instant};
+
+use chess_rust::bot::{evaluate, search, Limits, MATE};
+use chess_rust::chess::{square_from_name, Pieces};
+use chess_rust::fen::{translate_fen, STARTING_FEN};
+
+fn squares(from: &str, to: &str) -> (usize, usize) {
+    (square_from_name(from).unwrap(), square_from_name(to).unwrap())
+}
+
+#[test]
+fn start_position_is_balanced() {
+    let position = translate_fen(STARTING_FEN).unwrap();
+    assert_eq!(evaluate(&position), 0);
+}
+
+#[test]
+fn dead_positions_are_even() {
+    let position = translate_fen("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1").unwrap();
+    assert_eq!(evaluate(&position), 0);
+}
+
+#[test]
+fn finds_mate_in_one() {
+    let position = translate_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+    let info = search(&position, &Limits { depth: Some(3), time: None });
+
+    let mv = info.best_move().unwrap();
+    assert_eq!((mv.from, mv.to), squares("a1", "a8"));
+    assert_eq!(info.score, MATE - 1);
+}
+
+#[test]
+fn takes_the_hanging_queen() {
+    let position = translate_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+    let info = search(&position, &Limits { depth: Some(2), time: None });
+
+    let mv = info.best_move().unwrap();
+    assert_eq!((mv.from, mv.to), squares("d2", "d5"));
+}
+
+#[test]
+fn promotes_to_a_queen() {
+    let position = translate_fen("8/4P3/8/8/8/k7/8/6K1 w - - 0 1").unwrap();
+    let info = search(&position, &Limits { depth: Some(2), time: None });
+
+    assert_eq!(info.best_move().unwrap().promotion, Some(Pieces::QUEE));
+}
+
+#[test]
+fn respects_the_time_limit() {
+    let position = translate_fen(STARTING_FEN).unwrap();
+    let start = Instant::now();
+    let info = search(&position, &Limits { depth: None, time: Some(Duration::from_millis(200)) });
+
+    assert!(start.elapsed() < Duration::from_millis(600));
+    assert!(info.best_move().is_some());
+}
@@ -0,0 +1,247 @@
+use chess_rust::chess::{square_from_name, square_name, MoveFlag, MoveResult, Outcome, Pieces, Position};
+use chess_rust::fen::{to_fen, translate_fen};
+use chess_rust::uci::find_move;
+
+mod common;
+use common::play_game;
+
+fn square(name: &str) -> usize {
+    square_from_name(name).unwrap()
+}
+
+#[test]
+fn moves_know_what_they_do() {
+    let position = translate_fen("r3k2r/8/8/3pP3/8/8/4P3/R3K2R w KQkq d6 0 1").unwrap();
+
+    let push = position.find_move(square("e2"), square("e4"), None).unwrap();
+    assert_eq!(push.flag, MoveFlag::DoublePush);
+    assert_eq!(push.piece, Pieces::PAWN as i8 + 8);
+    assert!(!push.is_capture());
+
+    let en_passant = position.find_move(square("e5"), square("d6"), None).unwrap();
+    assert_eq!(en_passant.flag, MoveFlag::EnPassant);
+    assert_eq!(en_passant.captured, Pieces::PAWN as i8);
+
+    let castle = position.find_move(square("e1"), square("c1"), None).unwrap();
+    assert_eq!(castle.flag, MoveFlag::Castle);
+    assert_eq!(castle.name(), "e1c1");
+
+    let capture = position.find_move(square("a1"), square("a8"), None).unwrap();
+    assert_eq!(capture.flag, MoveFlag::Normal);
+    assert_eq!(capture.captured, Pieces::ROOK as i8);
+
+    assert_eq!(position.find_move(square("e2"), square("e5"), None), None);
+}
+
+#[test]
+fn promotions_are_played_in_one_go() {
+    let mut position = translate_fen("k7/4P3/1K6/8/8/8/8/8 w - - 0 1").unwrap();
+    let mv = position.find_move(square("e7"), square("e8"), Some(Pieces::QUEE)).unwrap();
+
+    assert_eq!(position.find_move(square("e7"), square("e8"), None), None);
+    assert_eq!(
+        position.make_move(mv),
+        vec![MoveResult::Promotion(Pieces::QUEE), MoveResult::Check, MoveResult::GameOver(Outcome::Checkmate { white_wins: true })]
+    );
+    assert_eq!(to_fen(&position), "k3Q3/8/1K6/8/8/8/8/8 b - - 0 1");
+}
+
+#[test]
+fn illegal_moves_fail() {
+    let mut position = translate_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+    let mv = position.new_move(square("e2"), square("e5"), None);
+
+    assert_eq!(position.make_move(mv), vec![MoveResult::Fail]);
+    assert_eq!(to_fen(&position), "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+}
+
+// the squares the piece on from may move to, by name
+fn targets(fen: &str, from: &str) -> Vec<String> {
+    let mut names: Vec<String> = translate_fen(fen).unwrap().legal_moves(square(from)).into_iter().map(square_name).collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn castling_needs_a_safe_king_path() {
+    let castles = |fen: &str| {
+        let position = translate_fen(fen).unwrap();
+        (position.find_move(square("e1"), square("g1"), None).is_some(), position.find_move(square("e1"), square("c1"), None).is_some())
+    };
+
+    assert_eq!(castles("6k1/8/8/8/8/8/8/R3K2R w KQ - 0 1"), (true, true));
+    // out of check
+    assert_eq!(castles("4r1k1/8/8/8/8/8/8/R3K2R w KQ - 0 1"), (false, false));
+    // through an attacked square
+    assert_eq!(castles("5rk1/8/8/8/8/8/8/R3K2R w KQ - 0 1"), (false, true));
+    assert_eq!(castles("3r2k1/8/8/8/8/8/8/R3K2R w KQ - 0 1"), (true, false));
+    // into check
+    assert_eq!(castles("1k4r1/8/8/8/8/8/8/R3K2R w KQ - 0 1"), (false, true));
+    assert_eq!(castles("2r3k1/8/8/8/8/8/8/R3K2R w KQ - 0 1"), (true, false));
+    // only the rook crosses b1, so an attack on it does not matter
+    assert_eq!(castles("1r4k1/8/8/8/8/8/8/R3K2R w KQ - 0 1"), (true, true));
+}
+
+#[test]
+fn pinned_pieces_stay_on_the_pin() {
+    // a bishop or knight pinned along a file or diagonal has no move at all
+    assert!(targets("4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1", "e2").is_empty());
+    assert!(targets("4k3/8/8/8/1b6/8/3N4/4K3 w - - 0 1", "d2").is_empty());
+    // a rook pinned on a file moves along it, up to taking the pinning piece
+    assert_eq!(targets("4k3/4r3/8/8/8/8/4R3/4K3 w - - 0 1", "e2"), ["e3", "e4", "e5", "e6", "e7"]);
+    // a pawn pinned on a diagonal may only take the pinning piece
+    assert_eq!(targets("4k3/8/8/8/8/2b5/3P4/4K3 w - - 0 1", "d2"), ["c3"]);
+    // once the king steps off the line the piece is free again
+    assert_eq!(targets("4k3/4r3/8/8/8/8/4B3/3K4 w - - 0 1", "e2").len(), 8);
+}
+
+#[test]
+fn moves_giving_check_say_so() {
+    let mut position = translate_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+    assert_eq!(play(&mut position, "a1a8"), vec![MoveResult::Check]);
+
+    let mut position = translate_fen("4k3/8/8/8/4p3/8/8/4RK2 w - - 0 1").unwrap();
+    assert_eq!(play(&mut position, "e1e4"), vec![MoveResult::Capture(Pieces::PAWN), MoveResult::Check]);
+
+    // the rook checks from f1 once the king has castled
+    let mut position = translate_fen("5k2/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+    assert_eq!(play(&mut position, "e1g1"), vec![MoveResult::Castle, MoveResult::Check]);
+
+    // a discovered check
+    let mut position = translate_fen("4k3/8/8/8/8/8/4N3/4R1K1 w - - 0 1").unwrap();
+    assert_eq!(play(&mut position, "e2c3"), vec![MoveResult::Check]);
+
+    let mut position = translate_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+    assert_eq!(play(&mut position, "a1a7"), vec![MoveResult::Move]);
+}
+
+// plays every move down to depth and checks that taking it back restores the position exactly
+fn check_unmake(position: &mut Position, depth: u32) {
+    if depth == 0 {
+        return;
+    }
+    for mv in position.legal_move_list() {
+        let before = position.clone();
+        let undo = position.play(mv);
+        check_unmake(position, depth - 1);
+        position.unmake_move(&undo);
+
+        assert_eq!(*position, before, "{} did not unmake", mv.name());
+    }
+}
+
+#[test]
+fn unmake_restores_the_position() {
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    ] {
+        check_unmake(&mut translate_fen(fen).unwrap(), 2);
+    }
+}
+
+// plays the move, named as in UCI, and returns what it did
+fn play(position: &mut Position, name: &str) -> Vec<MoveResult> {
+    let mv = find_move(position, name).unwrap();
+    position.make_move(mv)
+}
+
+#[test]
+fn keys_follow_the_moves() {
+    // castling, a double push allowing en passant, the capture itself and a promotion race
+    let moves = ["e1g1", "c7c5", "d5c6", "h3g2", "a2a4", "b4a3", "c6d7", "e7d7"];
+    let position = play_game("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &moves, None).position();
+    assert_eq!(position.key, position.compute_key());
+    assert_eq!(position.key, translate_fen(&to_fen(&position)).unwrap().key);
+
+    // the en passant square only matters when the capture is possible
+    let after_push = translate_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1").unwrap();
+    let no_square = translate_fen("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1").unwrap();
+    assert_eq!(after_push.key, no_square.key);
+}
+
+#[test]
+fn repetitions_can_be_claimed_then_end_the_game() {
+    let shuffle = ["g1f3", "e8d8", "f3g1", "d8e8"];
+    let mut position = play_game("4k3/8/4p3/4P3/8/8/8/4K1N1 w - - 0 1", &shuffle, None).position();
+    assert_eq!(position.repetitions(), 1);
+    assert_eq!(position.draw_claim(), None);
+
+    let results = shuffle.iter().map(|name| play(&mut position, name)).last().unwrap();
+    assert_eq!(results, vec![MoveResult::DrawClaimable(Outcome::ThreefoldRepetition)]);
+    assert_eq!(position.outcome(), None);
+
+    let results = shuffle.iter().chain(&shuffle).map(|name| play(&mut position, name)).last().unwrap();
+    assert_eq!(results, vec![MoveResult::GameOver(Outcome::FivefoldRepetition)]);
+}
+
+#[test]
+fn quiet_moves_run_out_the_clock() {
+    let mut position = translate_fen("4k3/8/4p3/4P3/8/8/8/4K1N1 w - - 99 80").unwrap();
+
+    assert_eq!(play(&mut position, "g1f3"), vec![MoveResult::DrawClaimable(Outcome::FiftyMoveRule)]);
+
+    let mut position = translate_fen("4k3/8/4p3/4P3/8/8/8/4K1N1 w - - 149 80").unwrap();
+    assert_eq!(play(&mut position, "g1f3"), vec![MoveResult::GameOver(Outcome::SeventyFiveMoveRule)]);
+
+    // a mate on the last move still wins
+    let mut position = translate_fen("7k/8/6K1/8/8/8/8/R7 w - - 149 80").unwrap();
+    assert_eq!(
+        play(&mut position, "a1a8"),
+        vec![MoveResult::Check, MoveResult::GameOver(Outcome::Checkmate { white_wins: true })]
+    );
+}
+
+#[test]
+fn bare_kings_and_lone_minors_cannot_mate() {
+    for fen in [
+        "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/2B1K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/4K1n1 w - - 0 1",
+        // bishops of both sides, all on dark squares
+        "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1",
+    ] {
+        let position = translate_fen(fen).unwrap();
+        assert!(position.insufficient_material(), "{}", fen);
+        assert_eq!(position.outcome(), Some(Outcome::InsufficientMaterial), "{}", fen);
+    }
+
+    for fen in [
+        "4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1",
+        "4k3/8/8/8/8/8/8/1N2K1N1 w - - 0 1",
+        "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/2B1Kn2 w - - 0 1",
+    ] {
+        assert!(!translate_fen(fen).unwrap().insufficient_material(), "{}", fen);
+    }
+
+    // taking the last piece that could mate ends the game
+    let mut position = translate_fen("4k3/8/8/8/8/8/3r4/2B1K3 w - - 0 1").unwrap();
+    assert_eq!(
+        play(&mut position, "e1d2"),
+        vec![MoveResult::Capture(Pieces::ROOK), MoveResult::GameOver(Outcome::InsufficientMaterial)]
+    );
+}
+
+#[test]
+fn mating_material_of_one_side() {
+    // (fen, white can mate, black can mate)
+    for (fen, white, black) in [
+        ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", false, false),
+        ("4k3/8/8/8/8/8/8/3QK3 w - - 0 1", true, false),
+        // a knight needs something in the way of the king, a queen never is
+        ("4k3/8/8/8/8/8/8/3qK1N1 w - - 0 1", false, true),
+        ("4k3/8/8/8/8/8/8/3rK1N1 w - - 0 1", true, true),
+        ("4k3/8/8/8/8/8/8/1N2K1N1 w - - 0 1", true, false),
+        // bishops of one color only mate when a pawn or knight blocks the king
+        ("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1", false, false),
+        ("4k3/4p3/8/8/8/8/8/2B1K3 w - - 0 1", true, true),
+        ("4k3/8/8/8/8/8/8/1Bb1K3 w - - 0 1", true, true),
+    ] {
+        let position = translate_fen(fen).unwrap();
+        assert_eq!(position.has_mating_material(true), white, "{}", fen);
+        assert_eq!(position.has_mating_material(false), black, "{}", fen);
+    }
+}
@@ -0,0 +1,98 @@
+use chess_rust::chess::{is_white, square_from_name, MoveFlag, Pieces};
+use chess_rust::chess960::{back_rank, start_position, START_POSITIONS, STANDARD_START};
+use chess_rust::fen::{piece_to_letter, to_fen, translate_fen, STARTING_FEN};
+use chess_rust::perft::perft;
+use chess_rust::san::{parse_san, to_san};
+
+fn letters(n: u32) -> String {
+    back_rank(n).unwrap().iter().map(|piece| piece_to_letter(*piece as i8 + 8)).collect()
+}
+
+fn square(name: &str) -> usize {
+    square_from_name(name).unwrap()
+}
+
+#[test]
+fn scharnagl_numbering() {
+    assert_eq!(letters(0), "BBQNNRKR");
+    assert_eq!(letters(STANDARD_START), "RNBQKBNR");
+    assert_eq!(letters(959), "RKRNNQBB");
+    assert_eq!(back_rank(START_POSITIONS), None);
+
+    let mut ranks: Vec<String> = (0..START_POSITIONS).map(letters).collect();
+    for rank in &ranks {
+        let bishops: Vec<usize> = rank.match_indices('B').map(|(file, _)| file).collect();
+        assert_ne!(bishops[0] % 2, bishops[1] % 2, "{}", rank);
+
+        let king = rank.find('K').unwrap();
+        assert!(rank.find('R').unwrap() < king && king < rank.rfind('R').unwrap(), "{}", rank);
+    }
+    ranks.sort();
+    ranks.dedup();
+    assert_eq!(ranks.len(), 960);
+}
+
+#[test]
+fn start_positions_are_x_fen() {
+    let position = start_position(STANDARD_START).unwrap();
+    assert_eq!(to_fen(&position), STARTING_FEN);
+    assert!(position.chess960);
+
+    let position = start_position(0).unwrap();
+    assert_eq!(to_fen(&position), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1");
+    assert_eq!(perft(&position, 1), 20);
+}
+
+#[test]
+fn castling_fields_name_the_rooks() {
+    // Shredder-FEN files and X-FEN letters describe the same rights
+    let shredder = translate_fen("1r2k1r1/8/8/8/8/8/8/RR2K3 w Bbg - 0 1").unwrap();
+    let x_fen = translate_fen("1r2k1r1/8/8/8/8/8/8/RR2K3 w Bkq - 0 1").unwrap();
+    assert_eq!(shredder, x_fen);
+    assert!(shredder.chess960);
+    assert_eq!(shredder.castling_rights.rook_file(true, false), 1);
+
+    // the inner rook keeps its file letter, the outer ones become K and Q
+    assert_eq!(to_fen(&shredder), "1r2k1r1/8/8/8/8/8/8/RR2K3 w Bkq - 0 1");
+    assert!(!translate_fen(STARTING_FEN).unwrap().chess960);
+    assert!(translate_fen("4k3/8/8/8/8/8/8/4K2R w D - 0 1").is_err());
+}
+
+#[test]
+fn castling_from_any_file() {
+    // the king on b1 castles long with the rook next to it and short across the board
+    let mut position = translate_fen("r3k2r/8/8/8/8/8/8/RK4R1 w AGah - 0 1").unwrap();
+
+    let long = position.find_move(square("b1"), square("a1"), None).unwrap();
+    assert_eq!(long.flag, MoveFlag::Castle);
+    assert_eq!(to_san(&position, &long), "O-O-O");
+
+    let short = parse_san(&position, "O-O").unwrap();
+    assert_eq!((short.from, short.to), (square("b1"), square("g1")));
+
+    let undo = position.play(short);
+    assert_eq!(to_fen(&position), "r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1");
+    position.unmake_move(&undo);
+
+    position.play(long);
+    assert_eq!(to_fen(&position), "r3k2r/8/8/8/8/8/8/2KR2R1 b kq - 1 1");
+    assert!(is_white(position.board[square("c1")]));
+    assert_eq!(position.board[square("d1")], Pieces::ROOK as i8 + 8);
+}
+
+// node counts from https://www.chessprogramming.org/Chess960_Perft_Results
+#[test]
+fn perft_positions() {
+    for (fen, expected) in [
+        ("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", [21, 528, 12189]),
+        ("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9", [21, 807, 18002]),
+        ("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9", [20, 479, 10471]),
+        ("qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9", [22, 593, 13440]),
+        ("1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9", [28, 1120, 31058]),
+    ] {
+        let position = translate_fen(fen).unwrap();
+        for (depth, nodes) in expected.iter().enumerate() {
+            assert_eq!(perft(&position, depth as u32 + 1), *nodes, "{} at depth {}", fen, depth + 1);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+use chess_rust::cli::{parse_args, Command, Options};
+use chess_rust::clock::{Increment, Stage};
+use chess_rust::fen::STARTING_FEN;
+
+fn parse(args: &[&str]) -> Result<Command, String> {
+    parse_args(args.iter().map(|arg| arg.to_string()))
+}
+
+fn play_options(args: &[&str]) -> Options {
+    match parse(args) {
+        Ok(Command::Play(options)) => options,
+        command => panic!("{:?}", command),
+    }
+}
+
+#[test]
+fn defaults() {
+    let options = play_options(&[]);
+
+    assert_eq!(options.fen, STARTING_FEN);
+    assert_eq!(options.bot, None);
+    assert_eq!((options.bot_limits.depth, options.bot_limits.time), (None, Some(Duration::from_secs(1))));
+    assert!(options.engine.is_none() && options.pgn.is_none() && options.save_pgn.is_none());
+    assert!(!options.debug_attacks);
+    assert_eq!(options.chess960, None);
+    assert_eq!(options.clock, None);
+}
+
+#[test]
+fn play_flags() {
+    let options = play_options(&[
+        "--fen", "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+        "--bot", "black",
+        "--engine", "stockfish",
+        "--debug-attacks",
+        "--pgn", "in.pgn",
+        "--save-pgn", "out.pgn",
+    ]);
+
+    assert_eq!(options.fen, "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+    assert_eq!(options.bot, Some(false));
+    assert_eq!(options.engine.as_deref(), Some("stockfish"));
+    assert!(options.debug_attacks);
+    assert_eq!(options.pgn.as_deref(), Some("in.pgn"));
+    assert_eq!(options.save_pgn.as_deref(), Some("out.pgn"));
+    assert_eq!(play_options(&["--bot", "white"]).bot, Some(true));
+    assert_eq!(play_options(&["--bot", "white", "--bot", "none"]).bot, None);
+}
+
+#[test]
+fn bot_limits() {
+    // a depth on its own searches without a time limit
+    let limits = play_options(&["--bot-depth", "6"]).bot_limits;
+    assert_eq!((limits.depth, limits.time), (Some(6), None));
+
+    let limits = play_options(&["--bot-time", "250"]).bot_limits;
+    assert_eq!((limits.depth, limits.time), (None, Some(Duration::from_millis(250))));
+
+    assert_eq!(parse(&["--bot-depth", "4294967296"]).unwrap_err(), "--bot-depth expects a number, found '4294967296'");
+    assert_eq!(parse(&["--bot-depth", "-1"]).unwrap_err(), "--bot-depth expects a number, found '-1'");
+    assert_eq!(parse(&["--bot-time", "soon"]).unwrap_err(), "--bot-time expects a number, found 'soon'");
+}
+
+#[test]
+fn chess960_starts() {
+    assert_eq!(play_options(&["--chess960", "518"]).chess960, Some(518));
+    assert_eq!(play_options(&["--chess960", "0"]).chess960, Some(0));
+    assert!(play_options(&["--chess960", "random"]).chess960.is_some_and(|n| n < 960));
+
+    for bad in ["960", "-1", "any"] {
+        assert_eq!(
+            parse(&["--chess960", bad]).unwrap_err(),
+            format!("--chess960 expects random or a number below 960, found '{}'", bad)
+        );
+    }
+}
+
+#[test]
+fn clocks() {
+    assert_eq!(
+        play_options(&["--clock", "3+2"]).clock,
+        Some(vec![Stage { moves: None, time: Duration::from_secs(180), increment: Some(Increment::Fischer(Duration::from_secs(2))) }])
+    );
+    assert_eq!(play_options(&["--clock", "40/90+30:30+30"]).clock.map(|stages| stages.len()), Some(2));
+
+    assert_eq!(
+        parse(&["--clock", "3+"]).unwrap_err(),
+        "--clock expects a time control such as 5, 3+2, 5d3 or 40/90+30:30+30, found '3+'"
+    );
+}
+
+#[test]
+fn perft() {
+    match parse(&["perft", "4", "--fen", "4k3/8/8/8/8/8/8/4K3 w - - 0 1"]) {
+        Ok(Command::Perft { depth, fen }) => assert_eq!((depth, fen.as_str()), (4, "4k3/8/8/8/8/8/8/4K3 w - - 0 1")),
+        command => panic!("{:?}", command),
+    }
+    match parse(&["perft", "2"]) {
+        Ok(Command::Perft { fen, .. }) => assert_eq!(fen, STARTING_FEN),
+        command => panic!("{:?}", command),
+    }
+
+    assert_eq!(parse(&["perft"]).unwrap_err(), "perft expects a depth");
+    assert_eq!(parse(&["perft", "deep"]).unwrap_err(), "invalid perft depth 'deep'");
+    assert_eq!(parse(&["perft", "3", "--bot", "white"]).unwrap_err(), "unknown argument '--bot'");
+}
+
+#[test]
+fn bad_arguments() {
+    assert_eq!(parse(&["--fen"]).unwrap_err(), "--fen expects a value");
+    assert_eq!(parse(&["--clock"]).unwrap_err(), "--clock expects a value");
+    assert_eq!(parse(&["--bot", "both"]).unwrap_err(), "--bot expects white, black or none, found 'both'");
+    assert_eq!(parse(&["--fast"]).unwrap_err(), "unknown argument '--fast'");
+}
@@ -0,0 +1,136 @@
+use std::time::{Duration, Instant};
+
+use chess_rust::chess::Outcome;
+use chess_rust::clock::{clock_text, flag_fall, parse_time_control, Clock, Increment, Stage};
+use chess_rust::fen::translate_fen;
+use chess_rust::game::Game;
+use chess_rust::uci::find_move;
+
+fn seconds(seconds: u64) -> Duration {
+    Duration::from_secs(seconds)
+}
+
+// both sides take the given number of seconds for each of their moves, white first
+fn play(clock: &mut Clock, start: Instant, moves: &[u64]) -> Instant {
+    let mut now = start;
+    clock.start(true, now);
+
+    for used in moves {
+        now += seconds(*used);
+        clock.punch(now);
+    }
+    now
+}
+
+#[test]
+fn parses_time_controls() {
+    assert_eq!(parse_time_control("5"), Some(vec![Stage { moves: None, time: seconds(300), increment: None }]));
+    assert_eq!(
+        parse_time_control("3+2"),
+        Some(vec![Stage { moves: None, time: seconds(180), increment: Some(Increment::Fischer(seconds(2))) }])
+    );
+    assert_eq!(
+        parse_time_control("0.5d3"),
+        Some(vec![Stage { moves: None, time: seconds(30), increment: Some(Increment::Bronstein(seconds(3))) }])
+    );
+    assert_eq!(
+        parse_time_control("40/90+30:30+30"),
+        Some(vec![
+            Stage { moves: Some(40), time: seconds(5400), increment: Some(Increment::Fischer(seconds(30))) },
+            Stage { moves: None, time: seconds(1800), increment: Some(Increment::Fischer(seconds(30))) },
+        ])
+    );
+
+    for text in ["", "0", "-5", "five", "0/5", "40/", "5+", "3+-2", "5:"] {
+        assert_eq!(parse_time_control(text), None, "{}", text);
+    }
+}
+
+#[test]
+fn sudden_death_only_counts_down() {
+    let mut clock = Clock::new(parse_time_control("5").unwrap());
+    let now = play(&mut clock, Instant::now(), &[10, 20, 30]);
+
+    // black's time comes first
+    assert_eq!(clock.remaining, [seconds(280), seconds(260)]);
+    assert_eq!(clock.time_left(false, now + seconds(15)), seconds(265));
+    assert_eq!(clock.time_left(true, now + seconds(15)), seconds(260));
+    assert_eq!(clock.flag_fallen(now + seconds(279)), None);
+    assert_eq!(clock.flag_fallen(now + seconds(280)), Some(false));
+}
+
+#[test]
+fn fischer_adds_and_bronstein_gives_back() {
+    let mut clock = Clock::new(parse_time_control("3+2").unwrap());
+    play(&mut clock, Instant::now(), &[1, 10]);
+    assert_eq!(clock.remaining, [seconds(172), seconds(181)]);
+
+    // never more than the delay and never more than the move took
+    let mut clock = Clock::new(parse_time_control("3d2").unwrap());
+    play(&mut clock, Instant::now(), &[1, 10]);
+    assert_eq!(clock.remaining, [seconds(172), seconds(180)]);
+}
+
+#[test]
+fn stages_add_their_time_after_their_moves() {
+    let mut clock = Clock::new(parse_time_control("2/10:5+1").unwrap());
+    assert_eq!(clock.moves_to_go(true), Some(2));
+    assert_eq!(clock.increment(true), Duration::ZERO);
+
+    play(&mut clock, Instant::now(), &[60, 60, 60]);
+    // white made its two moves and got five more minutes, black is one move short
+    assert_eq!(clock.remaining, [seconds(540), seconds(780)]);
+    assert_eq!(clock.moves_to_go(true), None);
+    assert_eq!(clock.increment(true), seconds(1));
+    assert_eq!(clock.moves_to_go(false), Some(1));
+
+    // a last stage with a move count starts over
+    let mut clock = Clock::new(parse_time_control("1/1").unwrap());
+    play(&mut clock, Instant::now(), &[30, 30, 30]);
+    assert_eq!(clock.remaining, [seconds(90), seconds(120)]);
+    assert_eq!(clock.moves_to_go(false), Some(1));
+}
+
+#[test]
+fn rewinding_takes_the_time_from_the_moves() {
+    let mut position = translate_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+    let mut game = Game::new(position.clone());
+    let mut clock = Clock::new(parse_time_control("1/1+5").unwrap());
+    let mut now = Instant::now();
+    clock.start(true, now);
+
+    for name in ["a1a2", "e8d8", "a2a3"] {
+        now += seconds(20);
+        clock.punch(now);
+        let mv = find_move(&position, name).unwrap();
+        game.record(&position, mv, Some(clock.remaining[position.is_white_turn as usize]));
+        position.play(mv);
+    }
+    let after = clock.clone();
+
+    game.undo();
+    clock.rewind(&game);
+    assert_eq!(clock.remaining, [seconds(105), seconds(105)]);
+    assert_eq!(clock.moves, [1, 1]);
+    assert_eq!(clock.running, None);
+
+    game.redo();
+    clock.rewind(&game);
+    assert_eq!((clock.remaining, clock.moves), (after.remaining, after.moves));
+}
+
+#[test]
+fn clock_text_rounds_up() {
+    assert_eq!(clock_text(seconds(5400)), "1:30:00");
+    assert_eq!(clock_text(seconds(3599)), "59:59");
+    assert_eq!(clock_text(Duration::from_millis(4001)), "0:05");
+    assert_eq!(clock_text(Duration::from_millis(1)), "0:01");
+    assert_eq!(clock_text(Duration::ZERO), "0:00");
+}
+
+#[test]
+fn flag_fall_is_a_draw_without_mating_material() {
+    let position = translate_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+    assert_eq!(flag_fall(&position, false), Outcome::Timeout { white_wins: true });
+    assert_eq!(flag_fall(&position, true), Outcome::TimeoutVsInsufficientMaterial);
+}
@@ -0,0 +1,55 @@
+// each test crate uses only some of these
+#![allow(dead_code)]
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use chess_rust::fen::translate_fen;
+use chess_rust::game::Game;
+use chess_rust::uci::find_move;
+
+// the game of the moves, named as in UCI, played from the FEN, each recorded with the clock
+pub fn play_game(fen: &str, names: &[&str], clock: Option<Duration>) -> Game {
+    let mut position = translate_fen(fen).unwrap();
+    let mut game = Game::new(position.clone());
+
+    for name in names {
+        let mv = find_move(&position, name).unwrap();
+        game.record(&position, mv, clock);
+        position.play(mv);
+    }
+    assert_eq!(game.position(), position);
+    game
+}
+
+// the commands opening a session in a protocol and the start of the last line the engine answers them with
+pub struct Protocol {
+    pub handshake: &'static str,
+    pub ready: &'static str,
+}
+
+pub const UCI: Protocol = Protocol { handshake: "uci\n", ready: "uciok" };
+pub const XBOARD: Protocol = Protocol { handshake: "xboard\nprotover 2\n", ready: "feature " };
+
+// feeds the whole script to the engine binary and returns what it printed
+pub fn run_script(script: &str) -> Vec<String> {
+    let mut engine = Command::new(env!("CARGO_BIN_EXE_engine"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    engine.stdin.take().unwrap().write_all(script.as_bytes()).unwrap();
+    let output = engine.wait_with_output().unwrap();
+
+    String::from_utf8(output.stdout).unwrap().lines().map(|line| line.to_string()).collect()
+}
+
+// opens a session in the protocol before the script and returns what the engine printed after the handshake
+pub fn run_engine(protocol: &Protocol, script: &str) -> Vec<String> {
+    let lines = run_script(&format!("{}{}", protocol.handshake, script));
+    let ready = lines.iter().position(|line| line.starts_with(protocol.ready)).unwrap();
+
+    lines[ready + 1..].to_vec()
+}
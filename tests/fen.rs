@@ -0,0 +1,48 @@
+use chess_rust::fen::{to_fen, translate_fen, FenError, STARTING_FEN};
+
+#[test]
+fn round_trips() {
+    let fens = [
+        STARTING_FEN,
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "4k3/8/8/8/8/8/8/4K3 b - - 37 102",
+    ];
+
+    for fen in fens {
+        let position = translate_fen(fen).unwrap();
+        assert_eq!(to_fen(&position), fen);
+    }
+}
+
+#[test]
+fn missing_clocks_default() {
+    let position = translate_fen("4k3/8/8/8/8/8/8/4K3 w - -").unwrap();
+    assert_eq!(to_fen(&position), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+}
+
+#[test]
+fn rejects_malformed_fens() {
+    let cases = [
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR", FenError::WrongFieldCount(1)),
+        ("rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1", FenError::WrongRankCount(7)),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNRR w - - 0 1", FenError::WrongSquareCount { rank: 7, squares: 9 }),
+        ("rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1", FenError::InvalidPiece('x')),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x - - 0 1", FenError::InvalidSideToMove("x".to_string())),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w KQkq - 0 1", FenError::InvalidCastling("KQkq".to_string())),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - e3 0 1", FenError::InvalidEnPassant("e3".to_string())),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - x 1", FenError::InvalidHalfmoveClock("x".to_string())),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 0", FenError::InvalidFullmoveNumber("0".to_string())),
+        ("rnbq1bnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1", FenError::MissingKing { white: false }),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBKKBNR w - - 0 1", FenError::TooManyKings { white: true }),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNp w - - 0 1", FenError::PawnOnBackRank(63)),
+        ("4k3/4R3/8/8/8/8/8/4K3 w - - 0 1", FenError::OpponentInCheck),
+    ];
+
+    for (fen, error) in cases {
+        assert_eq!(translate_fen(fen), Err(error), "{}", fen);
+    }
+}
@@ -0,0 +1,87 @@
+use chess_rust::fen::{to_fen, STARTING_FEN};
+use chess_rust::game::Game;
+use chess_rust::uci::find_move;
+
+mod common;
+use common::play_game;
+
+#[test]
+fn undo_restores_castling_and_en_passant() {
+    let mut game = play_game(STARTING_FEN, &["e2e4", "g8f6", "e4e5", "d7d5", "g1f3", "h8g8"], None);
+
+    assert!(game.undo());
+    assert!(game.undo());
+    assert_eq!(to_fen(&game.position()), "rnbqkb1r/ppp1pppp/5n2/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3");
+
+    assert!(game.redo());
+    assert!(game.redo());
+    assert!(!game.redo());
+    assert_eq!(to_fen(&game.position()), "rnbqkbr1/ppp1pppp/5n2/3pP3/8/5N2/PPPP1PPP/RNBQKB1R w KQq - 2 4");
+}
+
+#[test]
+fn recording_a_move_drops_the_undone_ones() {
+    let mut game = play_game(STARTING_FEN, &["e2e4", "e7e5"], None);
+    game.undo();
+
+    let position = game.position();
+    game.record(&position, find_move(&position, "c7c5").unwrap(), None);
+
+    assert!(!game.redo());
+    assert_eq!(game.moves.last().unwrap().san, "c5");
+}
+
+#[test]
+fn undo_to_turn_takes_back_the_reply_too() {
+    // the human plays white against the bot
+    let mut game = play_game(STARTING_FEN, &["e2e4", "e7e5", "g1f3", "b8c6"], None);
+
+    assert!(game.undo_to_turn(true));
+    assert_eq!(game.moves.len(), 2);
+    assert!(game.redo_to_turn(true));
+    assert_eq!(game.moves.len(), 4);
+
+    // with the human's move not answered yet only that move goes back
+    game.undo();
+    assert!(game.undo_to_turn(true));
+    assert_eq!(game.moves.len(), 2);
+
+    // the bot opening the game leaves nothing for the human to take back
+    let mut game = play_game(STARTING_FEN, &["e2e4"], None);
+    assert!(!game.undo_to_turn(false));
+    assert_eq!(game.moves.len(), 1);
+}
+
+#[test]
+fn goes_to_any_move_of_the_line() {
+    let mut game = play_game(STARTING_FEN, &["e2e4", "e7e5", "g1f3", "b8c6"], None);
+    let sans = |game: &Game| game.line().map(|game_move| game_move.san.clone()).collect::<Vec<_>>();
+
+    assert!(game.go_to(1));
+    assert_eq!(game.moves.len(), 1);
+    assert_eq!(sans(&game), ["e4", "e5", "Nf3", "Nc6"]);
+
+    assert!(game.go_to(3));
+    assert_eq!(to_fen(&game.position()), "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+    assert!(!game.go_to(3));
+    assert!(!game.go_to(5));
+    assert!(game.go_to(0));
+    assert_eq!(game.position(), game.start);
+}
+
+#[test]
+fn goes_to_the_side_on_move() {
+    let mut game = play_game(STARTING_FEN, &["e2e4", "e7e5", "g1f3", "b8c6"], None);
+
+    // black is to move after e4 and Nf3, so white lands just before them
+    assert!(game.go_to_turn(3, true));
+    assert_eq!(game.moves.len(), 2);
+    assert!(!game.go_to_turn(3, true));
+    assert!(game.go_to_turn(1, true));
+    assert_eq!(game.moves.len(), 0);
+    // black has no earlier move than its first
+    assert!(game.go_to_turn(0, false));
+    assert_eq!(game.moves.len(), 1);
+    assert!(game.go_to_turn(4, true));
+    assert_eq!(game.undone.len(), 0);
+}
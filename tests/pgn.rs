@@ -0,0 +1,178 @@
+use std::time::Duration;
+
+use chess_rust::chess::Outcome;
+use chess_rust::chess960::{start_position, STANDARD_START};
+use chess_rust::fen::{to_fen, STARTING_FEN};
+use chess_rust::game::{Game, GameMove};
+use chess_rust::pgn::{clock_comment, read_pgn, result_token, today, write_pgn, PgnError, Tags};
+use chess_rust::uci::find_move;
+
+mod common;
+use common::play_game;
+
+fn tags() -> Tags {
+    Tags { date: "2024.05.01".to_string(), white: "Human".to_string(), black: "bot".to_string(), ..Tags::default() }
+}
+
+#[test]
+fn fools_mate() {
+    let game = play_game(STARTING_FEN, &["f2f3", "e7e5", "g2g4", "d8h4"], None);
+
+    assert_eq!(
+        write_pgn(&game, &tags(), Some(Outcome::Checkmate { white_wins: false })),
+        "[Event \"Casual game\"]
+[Site \"ChessRust\"]
+[Date \"2024.05.01\"]
+[Round \"-\"]
+[White \"Human\"]
+[Black \"bot\"]
+[Result \"0-1\"]
+
+1. f3 e5 2. g4 Qh4# 0-1
+"
+    );
+}
+
+#[test]
+fn games_from_a_position_keep_their_fen() {
+    let fen = "4k3/8/8/8/8/8/4P3/4K3 b - - 0 7";
+    let pgn = write_pgn(&play_game(fen, &["e8d7", "e2e4"], None), &tags(), None);
+
+    assert!(pgn.contains("[Result \"*\"]\n[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 7\"]\n"));
+    assert!(pgn.ends_with("\n\n7... Kd7 8. e4 *\n"));
+}
+
+#[test]
+fn clocks_and_line_wrapping() {
+    let names = ["g1f3", "g8f6", "f3g1", "f6g8"].repeat(4);
+    let pgn = write_pgn(&play_game(STARTING_FEN, &names, Some(Duration::from_secs(299))), &tags(), None);
+    let movetext: Vec<&str> = pgn.split("\n\n").nth(1).unwrap().lines().collect();
+
+    assert!(movetext[0].starts_with("1. Nf3 {[%clk 0:04:59]} 1... Nf6 {[%clk 0:04:59]} 2. Ng1"));
+    assert!(movetext.iter().all(|line| line.len() < 80));
+    assert!(movetext.len() > 1);
+    assert!(movetext.join(" ").ends_with("8... Ng8 {[%clk 0:04:59]} *"));
+}
+
+#[test]
+fn tokens() {
+    assert_eq!(result_token(Some(Outcome::Checkmate { white_wins: true })), "1-0");
+    assert_eq!(result_token(Some(Outcome::Stalemate)), "1/2-1/2");
+    assert_eq!(clock_comment(Duration::from_secs(3909)), "{[%clk 1:05:09]}");
+
+    let date = today();
+    assert_eq!(date.len(), 10);
+    assert!(date.starts_with("20") && &date[4..5] == "." && &date[7..8] == ".");
+}
+
+const ANNOTATED: &str = r#"[Event "Club \"open\""]
+[Site "?"]
+[White "A"]
+[Black "B"]
+[Result "1-0"]
+
+{Opening comment} 1. e4 e5 2. Nf3 $1 Nc6!? (2... d6 {Philidor} 3. d4 (3. Bc4) 3... Nd7)
+(2... Nf6) 3. Bb5 ; the Spanish
+a6 4. Ba4 1-0
+
+[Event "Broken"]
+[Result "*"]
+
+1. e4 e5 2. Ke3 *
+
+[Event "From a position"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 0 7"]
+
+7... Kd7 8. e4 Kd6 *
+"#;
+
+#[test]
+fn reads_annotated_games() {
+    let games = read_pgn(ANNOTATED);
+    assert_eq!(games.len(), 3);
+
+    let game = games[0].as_ref().unwrap();
+    assert_eq!(game.tag("Event"), Some("Club \"open\""));
+    assert_eq!(game.result, "1-0");
+    assert_eq!(game.comments, vec!["Opening comment".to_string()]);
+
+    let main_line = game.main_line();
+    let main_line: Vec<&str> = main_line.moves.iter().map(|game_move| game_move.san.as_str()).collect();
+    assert_eq!(main_line, vec!["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4"]);
+
+    let knight = game.node(&[0, 0, 0]).unwrap();
+    assert_eq!(knight.nags, vec![1]);
+    let replies: Vec<&str> = knight.children.iter().map(|node| node.san.as_str()).collect();
+    assert_eq!(replies, vec!["Nc6", "d6", "Nf6"]);
+    assert_eq!(knight.children[0].nags, vec![5]);
+    assert_eq!(knight.children[1].comments, vec!["Philidor".to_string()]);
+    assert_eq!(game.node(&[0, 0, 0, 1, 1]).unwrap().san, "Bc4");
+    assert_eq!(game.node(&[0, 0, 0, 1, 0, 0]).unwrap().san, "Nd7");
+    assert_eq!(game.node(&[0, 0, 0, 0, 0]).unwrap().comments, vec!["the Spanish".to_string()]);
+}
+
+#[test]
+fn bad_games_do_not_stop_the_others() {
+    let games = read_pgn(ANNOTATED);
+
+    assert_eq!(
+        games[1],
+        Err(PgnError::IllegalMove { san: "Ke3".to_string(), fullmove_number: 2, white: true })
+    );
+    let game = games[2].as_ref().unwrap();
+    assert_eq!(to_fen(&game.position_at(&[0, 0, 0]).unwrap()), "8/8/3k4/8/4P3/8/8/4K3 w - - 1 9");
+}
+
+#[test]
+fn reads_en_passant_suffixes() {
+    let games = read_pgn("1. e4 Nf6 2. e5 d5 3. exd6 e.p. exd6 4. d4 *");
+    assert_eq!(games.len(), 1);
+
+    let main_line = games[0].as_ref().unwrap().main_line();
+    let main_line: Vec<&str> = main_line.moves.iter().map(|game_move| game_move.san.as_str()).collect();
+    assert_eq!(main_line, vec!["e4", "Nf6", "e5", "d5", "exd6", "exd6", "d4"]);
+}
+
+#[test]
+fn syntax_errors() {
+    assert_eq!(read_pgn("1. e4 {unfinished")[0], Err(PgnError::UnterminatedComment));
+    assert_eq!(read_pgn("1. e4 (1. d4 *")[0], Err(PgnError::UnterminatedVariation));
+    assert_eq!(read_pgn("(1. d4) 1. e4 *")[0], Err(PgnError::VariationWithoutMove));
+    assert_eq!(read_pgn("1. e4 ) *")[0], Err(PgnError::UnexpectedClose));
+    assert!(matches!(read_pgn("[Event \"x]\n1. e4 *")[0], Err(PgnError::InvalidTag(_))));
+}
+
+#[test]
+fn written_games_read_back() {
+    let game = play_game(STARTING_FEN, &["e2e4", "d7d5", "e4d5", "d8d5", "b1c3", "d5a5"], Some(Duration::from_secs(60)));
+    let pgn = write_pgn(&game, &tags(), None);
+    let read = read_pgn(&pgn);
+
+    assert_eq!(read.len(), 1);
+    let read = read[0].as_ref().unwrap();
+    assert_eq!(read.tag("White"), Some("Human"));
+    assert_eq!(read.result, "*");
+    assert_eq!(read.main_line(), Game { moves: game.moves.iter().map(|game_move| GameMove { clock: None, ..game_move.clone() }).collect(), ..game });
+}
+
+#[test]
+fn chess960_games_name_their_variant() {
+    let mut start = start_position(STANDARD_START).unwrap();
+    let mut game = Game::new(start.clone());
+    for name in ["e2e4", "e7e5", "g1f3", "g8f6", "f1c4", "f8c5", "e1h1"] {
+        let mv = find_move(&start, name).unwrap();
+        game.record(&start, mv, None);
+        start.play(mv);
+    }
+
+    let pgn = write_pgn(&game, &tags(), None);
+    assert!(pgn.contains("[Variant \"Chess960\"]\n"));
+    assert!(!pgn.contains("[FEN "));
+    assert!(pgn.contains("4. O-O *"));
+
+    let read = read_pgn(&pgn);
+    let read = read[0].as_ref().unwrap();
+    assert!(read.start.chess960);
+    assert_eq!(read.main_line().position(), start);
+}
@@ -0,0 +1,165 @@
+#![cfg(unix)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chess_rust::bot::Limits;
+use chess_rust::clock::{parse_time_control, Clock};
+use chess_rust::fen::STARTING_FEN;
+use chess_rust::player::{position_command, Player, UciEngine};
+use chess_rust::uci::find_move;
+
+mod common;
+use common::play_game;
+
+const PLAYS_E5: &str = "echo 'info depth 1 score cp 0 pv e7e5'; echo 'bestmove e7e5'";
+
+// a shell script answering the handshake and go with the given lines, e.g. always playing e7e5,
+// which logs the commands it got
+fn stub_engine(name: &str, go_reply: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("chess_rust_{}_{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let script = dir.join("stub.sh");
+    let log = dir.join("commands.log");
+
+    fs::write(&script, format!(
+        "#!/bin/sh
+while read -r line; do
+    echo \"$line\" >> '{}'
+    case \"$line\" in
+        uci) echo 'id name Stub'; echo 'uciok' ;;
+        isready) echo 'readyok' ;;
+        go*) {} ;;
+        quit) exit 0 ;;
+    esac
+done
+",
+        log.display(),
+        go_reply
+    )).unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+    (script, log)
+}
+
+#[test]
+fn stub_engine_plays_its_bestmove() {
+    let (script, log) = stub_engine("bestmove", PLAYS_E5);
+    let limits = Limits { depth: Some(3), time: Some(Duration::from_millis(100)) };
+    let game = play_game(STARTING_FEN, &["e2e4"], None);
+    let mut position = game.position();
+
+    let mut engine = UciEngine::start(script.to_str().unwrap(), limits).unwrap();
+    assert_eq!(engine.name, "Stub");
+
+    let mv = engine.best_move(&game, None, &AtomicBool::new(false)).unwrap();
+    assert_eq!(mv, find_move(&position, "e7e5"));
+    drop(engine);
+
+    let commands = fs::read_to_string(&log).unwrap();
+    assert_eq!(
+        commands.lines().collect::<Vec<_>>(),
+        vec![
+            "uci",
+            "ucinewgame",
+            "isready",
+            "position startpos moves e2e4",
+            "go depth 3 movetime 100",
+            "quit",
+        ]
+    );
+
+    position.play(mv.unwrap());
+    assert!(position.is_white_turn);
+}
+
+#[test]
+fn illegal_replies_are_errors() {
+    let (script, _) = stub_engine("illegal", PLAYS_E5);
+    let game = play_game(STARTING_FEN, &[], None);
+    let mut player = Player::Uci(UciEngine::start(script.to_str().unwrap(), Limits { depth: Some(1), time: None }).unwrap());
+
+    assert!(player.choose_move(&game, None, &AtomicBool::new(false)).is_err());
+}
+
+#[test]
+fn silent_engines_time_out() {
+    let (script, _) = stub_engine("silent", ":");
+    let game = play_game(STARTING_FEN, &[], None);
+    let mut player = Player::Uci(UciEngine::start(script.to_str().unwrap(), Limits { depth: None, time: Some(Duration::from_millis(100)) }).unwrap());
+
+    let error = player.choose_move(&game, None, &AtomicBool::new(false)).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+}
+
+#[test]
+fn hung_engines_are_killed() {
+    let (script, _) = stub_engine("hung", "sleep 60");
+    let game = play_game(STARTING_FEN, &[], None);
+    let mut engine = UciEngine::start(script.to_str().unwrap(), Limits { depth: None, time: Some(Duration::from_millis(100)) }).unwrap();
+
+    let started = Instant::now();
+    assert!(engine.best_move(&game, None, &AtomicBool::new(false)).is_err());
+    drop(engine);
+    assert!(started.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn positions_come_with_their_moves() {
+    let game = play_game("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1", &["e1c1", "e8e7"], None);
+    assert_eq!(position_command(&game), "position fen 4k3/8/8/8/8/8/8/R3K3 w Q - 0 1 moves e1c1 e8e7");
+    assert_eq!(position_command(&play_game(STARTING_FEN, &[], None)), "position startpos");
+}
+
+#[test]
+fn missing_engines_fail_to_start() {
+    assert!(UciEngine::start("/nonexistent/engine", Limits { depth: Some(1), time: None }).is_err());
+}
+
+#[test]
+fn plays_against_the_engine_binary() {
+    let game = play_game("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", &[], None);
+    let mut player = Player::Uci(UciEngine::start(env!("CARGO_BIN_EXE_engine"), Limits { depth: Some(3), time: None }).unwrap());
+
+    assert_eq!(player.name(), "ChessRust");
+    assert_eq!(player.choose_move(&game, None, &AtomicBool::new(false)).unwrap(), find_move(&game.start, "a1a8"));
+}
+
+#[test]
+fn stopped_engines_play_what_they_found() {
+    let game = play_game(STARTING_FEN, &["e2e4"], None);
+    let mut player = Player::Uci(UciEngine::start(env!("CARGO_BIN_EXE_engine"), Limits { depth: None, time: None }).unwrap());
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let stopper = {
+        let stop = Arc::clone(&stop);
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            stop.store(true, Ordering::Relaxed);
+        })
+    };
+    let mv = player.choose_move(&game, None, &stop).unwrap();
+    stopper.join().unwrap();
+    assert!(mv.is_some());
+}
+
+#[test]
+fn the_clock_replaces_the_move_time() {
+    let (script, log) = stub_engine("clock", PLAYS_E5);
+    let limits = Limits { depth: None, time: Some(Duration::from_millis(100)) };
+    let game = play_game(STARTING_FEN, &["e2e4"], None);
+    let mut clock = Clock::new(parse_time_control("40/90+30:30+30").unwrap());
+    clock.remaining[1] = Duration::from_secs(5390);
+
+    let mut engine = UciEngine::start(script.to_str().unwrap(), limits).unwrap();
+    engine.best_move(&game, Some(&clock), &AtomicBool::new(false)).unwrap();
+    drop(engine);
+
+    let commands = fs::read_to_string(&log).unwrap();
+    assert!(commands.lines().any(|line| line == "go wtime 5390000 btime 5400000 winc 30000 binc 30000 movestogo 40"));
+}
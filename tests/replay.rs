@@ -0,0 +1,74 @@
+use chess_rust::fen::to_fen;
+use chess_rust::pgn::read_pgn;
+use chess_rust::replay::Replay;
+
+fn replay() -> Replay {
+    let text = "1. e4 e5 (1... c5 2. Nf3) 2. Nf3 *\n\n[Event \"Second\"]\n\n1. d4 *\n";
+
+    Replay::new(read_pgn(text).into_iter().map(|game| game.unwrap()).collect())
+}
+
+fn last_san(replay: &Replay) -> Option<&str> {
+    replay.last_move().map(|node| node.san.as_str())
+}
+
+#[test]
+fn steps_through_the_main_line() {
+    let mut replay = replay();
+
+    assert!(!replay.back());
+    assert!(replay.forward());
+    assert!(replay.forward());
+    assert_eq!(last_san(&replay), Some("e5"));
+
+    replay.to_end();
+    assert_eq!(last_san(&replay), Some("Nf3"));
+    assert!(!replay.forward());
+    assert_eq!(to_fen(&replay.position()), "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+
+    replay.to_start();
+    assert_eq!(last_san(&replay), None);
+}
+
+#[test]
+fn switches_variations_and_games() {
+    let mut replay = replay();
+
+    replay.forward();
+    assert!(!replay.switch_variation(true));
+    replay.forward();
+    assert!(replay.switch_variation(true));
+    assert_eq!(last_san(&replay), Some("c5"));
+    replay.forward();
+    assert_eq!(last_san(&replay), Some("Nf3"));
+    assert!(replay.back());
+    assert!(replay.switch_variation(false));
+    assert_eq!(last_san(&replay), Some("e5"));
+
+    assert!(!replay.switch_game(false));
+    assert!(replay.switch_game(true));
+    assert_eq!(replay.current().tag("Event"), Some("Second"));
+    assert_eq!(last_san(&replay), None);
+    assert!(!replay.switch_game(true));
+}
+
+#[test]
+fn lists_and_jumps_along_the_line_shown() {
+    let mut replay = replay();
+    let sans = |replay: &Replay| replay.line().iter().map(|node| node.san.clone()).collect::<Vec<_>>();
+
+    assert_eq!(sans(&replay), ["e4", "e5", "Nf3"]);
+    replay.forward();
+    replay.forward();
+    replay.switch_variation(true);
+    assert_eq!(sans(&replay), ["e4", "c5", "Nf3"]);
+
+    assert!(replay.go_to(3));
+    assert_eq!(last_san(&replay), Some("Nf3"));
+    assert_eq!(replay.path, [0, 1, 0]);
+    assert!(!replay.go_to(3));
+    assert!(replay.go_to(1));
+    assert_eq!(last_san(&replay), Some("e4"));
+    // the variation is left along with the move that started it
+    assert_eq!(sans(&replay), ["e4", "e5", "Nf3"]);
+}
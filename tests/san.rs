@@ -0,0 +1,92 @@
+use chess_rust::fen::translate_fen;
+use chess_rust::san::{move_number, parse_san, to_display_san, to_san};
+use chess_rust::uci::find_move;
+
+fn san_of(fen: &str, name: &str) -> String {
+    let position = translate_fen(fen).unwrap();
+
+    to_san(&position, &find_move(&position, name).unwrap())
+}
+
+#[test]
+fn pieces_and_pawns() {
+    let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    assert_eq!(san_of(start, "e2e4"), "e4");
+    assert_eq!(san_of(start, "g1f3"), "Nf3");
+    assert_eq!(san_of("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2", "e4d5"), "exd5");
+    assert_eq!(san_of("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", "e5f6"), "exf6");
+}
+
+#[test]
+fn disambiguation() {
+    // knights on b8 and f6 both reach d7
+    assert_eq!(san_of("rn2k3/8/5n2/8/8/8/8/4K3 b - - 0 1", "b8d7"), "Nbd7");
+    // rooks on a1 and a5 both reach a3
+    assert_eq!(san_of("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "a1a3"), "R1a3");
+    // queens on h4, e4 and h1 all reach e1
+    assert_eq!(san_of("2k5/8/8/8/4Q2Q/8/8/K6Q w - - 0 1", "h4e1"), "Qh4e1");
+}
+
+#[test]
+fn castling_promotion_check_and_mate() {
+    assert_eq!(san_of("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1g1"), "O-O");
+    assert_eq!(san_of("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "e8c8"), "O-O-O");
+    assert_eq!(san_of("7k/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8q"), "e8=Q+");
+    assert_eq!(san_of("7k/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8n"), "e8=N");
+    assert_eq!(san_of("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2", "d8h4"), "Qh4#");
+}
+
+#[test]
+fn parses_san() {
+    let position = translate_fen("r3k2r/1P6/8/3pP3/8/2N3N1/8/R3K2R w KQkq d6 0 1").unwrap();
+    let parse = |san: &str| parse_san(&position, san);
+
+    assert_eq!(parse("O-O"), find_move(&position, "e1g1"));
+    assert_eq!(parse("0-0-0"), find_move(&position, "e1c1"));
+    assert_eq!(parse("exd6"), find_move(&position, "e5d6"));
+    assert_eq!(parse("bxa8=N+"), find_move(&position, "b7a8n"));
+    assert_eq!(parse("b8Q"), find_move(&position, "b7b8q"));
+    assert_eq!(parse("Nce4!?"), find_move(&position, "c3e4"));
+    assert_eq!(parse("Ng3-e4"), find_move(&position, "g3e4"));
+    // two knights reach e4 and b8 needs a promotion piece
+    assert_eq!(parse("Ne4"), None);
+    assert_eq!(parse("b8"), None);
+    assert_eq!(parse("Ke3"), None);
+    assert_eq!(parse("hello"), None);
+}
+
+#[test]
+fn en_passant_is_spelled_out_for_display_only() {
+    let position = translate_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3").unwrap();
+    let capture = find_move(&position, "e5f6").unwrap();
+
+    assert_eq!(to_display_san(&position, &capture), "exf6 e.p.");
+    assert_eq!(to_display_san(&position, &find_move(&position, "g1f3").unwrap()), "Nf3");
+    assert_eq!(parse_san(&position, "exf6 e.p."), Some(capture));
+    assert_eq!(parse_san(&position, "exf6e.p."), Some(capture));
+    assert_eq!(move_number(&position), "3.");
+}
+
+// every legal move of a few busy positions reads back as itself, with no two moves written alike
+#[test]
+fn round_trips() {
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "2k5/8/8/8/4Q2Q/8/8/K6Q w - - 0 1",
+    ] {
+        let position = translate_fen(fen).unwrap();
+        let mut names: Vec<String> = Vec::new();
+
+        for mv in position.legal_move_list() {
+            let san = to_san(&position, &mv);
+            assert_eq!(parse_san(&position, &san), Some(mv), "{} in {}", san, fen);
+            assert_eq!(parse_san(&position, &to_display_san(&position, &mv)), Some(mv));
+            assert!(!names.contains(&san), "{} written twice in {}", san, fen);
+            names.push(san);
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use chess_rust::bot::MATE;
+use chess_rust::uci::{parse_go, score_string};
+
+mod common;
+use common::{run_engine, run_script, UCI};
+
+#[test]
+fn handshake() {
+    let lines = run_script("uci\nisready\nquit\n");
+
+    assert!(lines[0].starts_with("id name"));
+    assert!(lines.contains(&"uciok".to_string()));
+    assert!(lines.contains(&"option name UCI_Chess960 type check default false".to_string()));
+    assert_eq!(lines.last().unwrap(), "readyok");
+}
+
+#[test]
+fn plays_a_legal_reply_after_moves() {
+    let lines = run_engine(&UCI, "position startpos moves e2e4 e7e5 g1f3\ngo depth 2\n");
+
+    assert!(lines.iter().any(|line| line.starts_with("info depth 2 score cp")));
+    let best_move = lines.last().unwrap();
+    assert!(best_move.starts_with("bestmove "));
+    assert_eq!(best_move.len(), "bestmove b8c6".len());
+}
+
+#[test]
+fn finds_mate_from_fen() {
+    let lines = run_engine(&UCI, "position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\ngo depth 3\n");
+
+    assert!(lines.iter().any(|line| line.contains("score mate 1") && line.ends_with("pv a1a8")));
+    assert_eq!(lines.last().unwrap(), "bestmove a1a8");
+}
+
+#[test]
+fn stop_ends_an_infinite_search() {
+    let lines = run_engine(&UCI, "position startpos\ngo infinite\nstop\nquit\n");
+
+    assert!(lines.last().unwrap().starts_with("bestmove "));
+}
+
+#[test]
+fn reports_illegal_moves() {
+    let lines = run_engine(&UCI, "position startpos moves e2e5\nquit\n");
+
+    assert_eq!(lines, vec!["info string illegal move e2e5".to_string()]);
+}
+
+#[test]
+fn go_limits() {
+    let (limits, infinite) = parse_go(&["depth", "5"], true);
+    assert_eq!((limits.depth, limits.time, infinite), (Some(5), None, false));
+
+    let (limits, _) = parse_go(&["movetime", "250"], true);
+    assert_eq!(limits.time, Some(Duration::from_millis(250)));
+
+    // black's clock divided over 30 moves, plus half the increment, less the overhead
+    let (limits, _) = parse_go(&["wtime", "1000", "btime", "60000", "winc", "0", "binc", "1000"], false);
+    assert_eq!(limits.time, Some(Duration::from_millis(2000 + 500 - 30)));
+
+    let (limits, infinite) = parse_go(&["infinite"], true);
+    assert_eq!((limits.depth, limits.time, infinite), (None, None, true));
+}
+
+#[test]
+fn mate_scores_count_moves() {
+    assert_eq!(score_string(35), "cp 35");
+    assert_eq!(score_string(MATE - 1), "mate 1");
+    assert_eq!(score_string(MATE - 3), "mate 2");
+    assert_eq!(score_string(-MATE + 2), "mate -1");
+}
+
+#[test]
+fn chess960_castling_takes_the_rook() {
+    let lines = run_engine(&UCI, "setoption name UCI_Chess960 value true\nposition startpos moves e2e4 e7e5 g1f3 g8f6 f1c4 f8c5 e1h1 e8h8\ngo depth 1\n");
+
+    assert!(!lines.iter().any(|line| line.starts_with("info string")), "{:?}", lines);
+    assert!(lines.last().unwrap().starts_with("bestmove "));
+
+    // without the option the same moves are not castling
+    let lines = run_engine(&UCI, "position startpos moves e2e4 e7e5 g1f3 g8f6 f1c4 f8c5 e1h1\n");
+    assert_eq!(lines, vec!["info string illegal move e1h1".to_string()]);
+}
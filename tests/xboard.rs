@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use chess_rust::bot::MATE;
+use chess_rust::xboard::{parse_seconds, score_value};
+
+mod common;
+use common::{run_engine, run_script, XBOARD};
+
+#[test]
+fn handshake() {
+    let lines = run_script("xboard\nprotover 2\nping 7\nquit\n");
+
+    assert!(lines[0].starts_with("feature ") && lines[0].contains("setboard=1") && lines[0].ends_with("done=1"));
+    assert_eq!(lines.last().unwrap(), "pong 7");
+}
+
+#[test]
+fn replies_to_a_user_move() {
+    let lines = run_engine(&XBOARD, "new\nsd 2\nusermove e2e4\nping 1\n");
+
+    let reply = lines.iter().find(|line| line.starts_with("move ")).unwrap();
+    assert_eq!(reply.len(), "move e7e5".len());
+    assert!(lines.contains(&"pong 1".to_string()));
+}
+
+#[test]
+fn answers_ping_while_thinking() {
+    // the search would take half a minute, "?" cuts it short after the pong
+    let lines = run_engine(&XBOARD, "new\nst 30\nusermove e2e4\nping 1\n?\nquit\n");
+
+    let pong = lines.iter().position(|line| line == "pong 1").unwrap();
+    let reply = lines.iter().position(|line| line.starts_with("move ")).unwrap();
+    assert!(pong < reply);
+}
+
+#[test]
+fn moves_without_any_limit_set() {
+    let lines = run_engine(&XBOARD, "new\nusermove e2e4\n");
+
+    assert!(lines.iter().any(|line| line.starts_with("move ")));
+}
+
+#[test]
+fn force_mode_only_records_moves() {
+    let lines = run_engine(&XBOARD, "new\nforce\ne2e4\ne7e5\nping 1\n");
+
+    assert_eq!(lines, vec!["pong 1".to_string()]);
+}
+
+#[test]
+fn go_mates_from_setboard() {
+    let lines = run_engine(&XBOARD, "force\nsetboard 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\nsd 3\npost\ngo\n");
+
+    assert!(lines.iter().any(|line| line.starts_with("1 100001 ")));
+    assert_eq!(lines[lines.len() - 2], "move a1a8");
+    assert_eq!(lines.last().unwrap(), "1-0 {White mates}");
+}
+
+#[test]
+fn undo_takes_moves_back() {
+    let lines = run_engine(&XBOARD, "force\ne2e4\nundo\nusermove e2e5\ne2e4\ne7e5\nremove\ne2e4\nping 1\n");
+
+    assert_eq!(lines, vec!["Illegal move: e2e5".to_string(), "pong 1".to_string()]);
+}
+
+#[test]
+fn rejects_bad_positions() {
+    let lines = run_engine(&XBOARD, "setboard 8/8/8/8/8/8/8/8 w - - 0 1\n");
+
+    assert!(lines[0].starts_with("tellusererror Illegal position"));
+}
+
+#[test]
+fn mate_scores() {
+    assert_eq!(score_value(35), 35);
+    assert_eq!(score_value(MATE - 3), 100_002);
+    assert_eq!(score_value(-MATE + 2), -100_001);
+}
+
+#[test]
+fn increments_may_have_fractions() {
+    assert_eq!(parse_seconds("12.5"), Some(Duration::from_millis(12_500)));
+    assert_eq!(parse_seconds("0.5"), Some(Duration::from_millis(500)));
+    assert_eq!(parse_seconds("2"), Some(Duration::from_secs(2)));
+    assert_eq!(parse_seconds("-1"), None);
+    assert_eq!(parse_seconds("x"), None);
+}